@@ -3,6 +3,7 @@ use std::mem::swap;
 use crate::{
     randoms::{max, min},
     ray::Ray,
+    stats,
     vec3::Point3,
 };
 
@@ -13,6 +14,7 @@ pub struct AABB {
 }
 impl AABB {
     pub fn hit(&self, r: &Ray, t_min: f64, t_max: f64) -> bool {
+        stats::box_test();
         let inv_d = 1.0 / r.dir.e.0;
         let mut t0 = (self.minimum.e.0 - r.orig.e.0) * inv_d;
         let mut t1 = (self.maximum.e.0 - r.orig.e.0) * inv_d;
@@ -50,6 +52,34 @@ impl AABB {
         }
         true
     }
+    //与 hit 相同的 slab 测试，方向的倒数与起点按轴预先算好，遍历 BVH 时每条光线只算一次
+    #[inline]
+    pub fn hit_inv(&self, orig: &[f64; 3], inv_dir: &[f64; 3], t_min: f64, t_max: f64) -> bool {
+        stats::box_test();
+        let lo = [self.minimum.e.0, self.minimum.e.1, self.minimum.e.2];
+        let hi = [self.maximum.e.0, self.maximum.e.1, self.maximum.e.2];
+        let (mut temp_min, mut temp_max) = (t_min, t_max);
+        for a in 0..3 {
+            let mut t0 = (lo[a] - orig[a]) * inv_dir[a];
+            let mut t1 = (hi[a] - orig[a]) * inv_dir[a];
+            if inv_dir[a] < 0.0 {
+                swap(&mut t0, &mut t1);
+            }
+            temp_min = if t0 > temp_min { t0 } else { temp_min };
+            temp_max = if t1 < temp_max { t1 } else { temp_max };
+            if temp_max <= temp_min {
+                return false;
+            }
+        }
+        true
+    }
+    pub fn surface_area(&self) -> f64 {
+        let d = self.maximum - self.minimum;
+        2.0 * (d.e.0 * d.e.1 + d.e.1 * d.e.2 + d.e.2 * d.e.0)
+    }
+    pub fn centroid(&self) -> Point3 {
+        (self.minimum + self.maximum) * 0.5
+    }
 }
 pub fn surrounding_box(box0: &AABB, box1: &AABB) -> AABB {
     let small = Point3 {
@@ -0,0 +1,42 @@
+//关键帧插值：按帧号排序后在相邻两个关键帧之间线性插值，第一帧之前与最后一帧之后取端点的值
+pub trait Lerp: Copy {
+    fn lerp(a: Self, b: Self, t: f64) -> Self;
+}
+
+impl Lerp for f64 {
+    fn lerp(a: Self, b: Self, t: f64) -> Self {
+        a + (b - a) * t
+    }
+}
+
+impl Lerp for [f64; 3] {
+    fn lerp(a: Self, b: Self, t: f64) -> Self {
+        [
+            f64::lerp(a[0], b[0], t),
+            f64::lerp(a[1], b[1], t),
+            f64::lerp(a[2], b[2], t),
+        ]
+    }
+}
+
+//keys 为 (帧号, 值)，没有关键帧时返回 None
+pub fn interpolate<T: Lerp>(keys: impl IntoIterator<Item = (f64, T)>, frame: f64) -> Option<T> {
+    let mut keys: Vec<(f64, T)> = keys.into_iter().collect();
+    keys.sort_by(|a, b| a.0.partial_cmp(&b.0).unwrap());
+    let first = *keys.first()?;
+    if frame <= first.0 {
+        return Some(first.1);
+    }
+    for w in keys.windows(2) {
+        let ((f0, v0), (f1, v1)) = (w[0], w[1]);
+        if frame <= f1 {
+            let t = if f1 > f0 {
+                (frame - f0) / (f1 - f0)
+            } else {
+                1.0
+            };
+            return Some(T::lerp(v0, v1, t));
+        }
+    }
+    keys.last().map(|k| k.1)
+}
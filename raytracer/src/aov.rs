@@ -0,0 +1,240 @@
+use crate::{
+    framebuffer::HdrImage,
+    integrator::PathSample,
+    vec3::{Color, Vec3},
+};
+
+//除 Emitted、Scattered 外都只看相机光线第一次击中的地方
+#[derive(Clone, Copy, PartialEq)]
+pub enum Aov {
+    //着色用的法线，朝向入射光线一侧
+    ShadingNormal,
+    //物体表面向外的法线
+    GeometricNormal,
+    Albedo,
+    Depth,
+    Position,
+    Uv,
+    ObjectId,
+    MaterialId,
+    //第一次击中处的自发光，相机光线没击中时为背景
+    Emitted,
+    //其余经过散射得到的辐亮度，与 Emitted 相加即为结果图
+    Scattered,
+}
+
+pub const AOV_NAMES: [&str; 10] = [
+    "shading_normal",
+    "geometric_normal",
+    "albedo",
+    "depth",
+    "position",
+    "uv",
+    "object_id",
+    "material_id",
+    "emitted",
+    "scattered",
+];
+const AOVS: [Aov; 10] = [
+    Aov::ShadingNormal,
+    Aov::GeometricNormal,
+    Aov::Albedo,
+    Aov::Depth,
+    Aov::Position,
+    Aov::Uv,
+    Aov::ObjectId,
+    Aov::MaterialId,
+    Aov::Emitted,
+    Aov::Scattered,
+];
+
+impl Aov {
+    //"all" 表示全部
+    pub fn parse_list(names: &[String]) -> Result<Vec<Aov>, String> {
+        let mut aovs = vec![];
+        for name in names {
+            if name == "all" {
+                aovs = AOVS.to_vec();
+                continue;
+            }
+            match AOV_NAMES.iter().position(|n| n == name) {
+                Some(k) if !aovs.contains(&AOVS[k]) => aovs.push(AOVS[k]),
+                Some(_) => {}
+                None => {
+                    return Err(format!(
+                        "unknown AOV \"{}\", use all or {}",
+                        name,
+                        AOV_NAMES.join(", ")
+                    ))
+                }
+            }
+        }
+        Ok(aovs)
+    }
+    pub fn name(&self) -> &'static str {
+        AOV_NAMES[AOVS.iter().position(|a| a == self).unwrap()]
+    }
+    //Emitted、Scattered 是辐亮度，8 位输出时与结果图一样做色调映射
+    pub fn is_radiance(&self) -> bool {
+        matches!(self, Aov::Emitted | Aov::Scattered)
+    }
+    //编号不能平均，只取像素第一个击中的采样
+    fn is_id(&self) -> bool {
+        matches!(self, Aov::ObjectId | Aov::MaterialId)
+    }
+    //这个采样在该 AOV 上的值，没击中时为 None
+    fn value(&self, s: &PathSample) -> Option<Color> {
+        if self.is_radiance() {
+            return Some(match self {
+                Aov::Emitted => s.emitted,
+                _ => s.radiance - s.emitted,
+            });
+        }
+        let h = s.first_hit.as_ref()?;
+        Some(match self {
+            Aov::ShadingNormal => h.shading_normal,
+            Aov::GeometricNormal => h.geometric_normal,
+            Aov::Albedo => h.albedo,
+            Aov::Depth => Color { e: (h.t, h.t, h.t) },
+            Aov::Position => h.p,
+            Aov::Uv => Color { e: (h.u, h.v, 0.0) },
+            Aov::ObjectId => Color {
+                e: (h.object_id as f64, 0.0, 0.0),
+            },
+            _ => Color {
+                e: (h.material_id as f64, 0.0, 0.0),
+            },
+        })
+    }
+    //8 位输出时映射到 [0,1]：法线 (n+1)/2，深度与位置按整张图的范围归一化，编号换成随机颜色
+    pub fn display(&self, img: &HdrImage) -> HdrImage {
+        let data = match self {
+            Aov::ShadingNormal | Aov::GeometricNormal => img
+                .data
+                .iter()
+                .map(|p| p.map(|x| 0.5 * (x + 1.0)))
+                .collect(),
+            Aov::Depth | Aov::Position => {
+                let mut lo = [f32::INFINITY; 3];
+                let mut hi = [f32::NEG_INFINITY; 3];
+                for p in img.data.iter().filter(|p| p.iter().any(|&x| x != 0.0)) {
+                    for c in 0..3 {
+                        lo[c] = lo[c].min(p[c]);
+                        hi[c] = hi[c].max(p[c]);
+                    }
+                }
+                img.data
+                    .iter()
+                    .map(|p| {
+                        let mut q = [0.0; 3];
+                        for c in 0..3 {
+                            if hi[c] > lo[c] {
+                                q[c] = (p[c] - lo[c]) / (hi[c] - lo[c]);
+                            }
+                        }
+                        q
+                    })
+                    .collect()
+            }
+            Aov::ObjectId | Aov::MaterialId => {
+                img.data.iter().map(|p| id_color(p[0] as u32)).collect()
+            }
+            _ => img.data.clone(),
+        };
+        HdrImage {
+            width: img.width,
+            height: img.height,
+            data,
+        }
+    }
+}
+
+//编号 0 为黑色，其余按整数哈希取一个较亮的颜色
+fn id_color(id: u32) -> [f32; 3] {
+    if id == 0 {
+        return [0.0; 3];
+    }
+    let mut h = id.wrapping_mul(0x9e37_79b9);
+    h ^= h >> 16;
+    h = h.wrapping_mul(0x85eb_ca6b);
+    h ^= h >> 13;
+    let c = |k: u32| 0.2 + 0.8 * ((h >> k) & 0xff) as f32 / 255.0;
+    [c(0), c(8), c(16)]
+}
+
+//一块区域（一个 tile 或整张图）上各 AOV 的和与计数，按 [像素][AOV] 存储
+pub struct AovBuffer {
+    pub aovs: Vec<Aov>,
+    pub width: u32,
+    pub height: u32,
+    pub sum: Vec<Color>,
+    pub count: Vec<u32>,
+}
+
+impl AovBuffer {
+    pub fn new(width: u32, height: u32, aovs: &[Aov]) -> Self {
+        let n = (width * height) as usize * aovs.len();
+        Self {
+            aovs: aovs.to_vec(),
+            width,
+            height,
+            sum: vec![Vec3::new(); n],
+            count: vec![0; n],
+        }
+    }
+    //x,y 为区域内坐标
+    pub fn add(&mut self, x: u32, y: u32, s: &PathSample) {
+        let base = (y * self.width + x) as usize * self.aovs.len();
+        for (k, aov) in self.aovs.iter().enumerate() {
+            let idx = base + k;
+            if aov.is_id() && self.count[idx] > 0 {
+                continue;
+            }
+            if let Some(c) = aov.value(s) {
+                self.sum[idx] += c;
+                self.count[idx] += 1;
+            }
+        }
+    }
+    //把 other 合并到本区域的 (x0,y0) 处
+    pub fn add_region(&mut self, other: &AovBuffer, x0: u32, y0: u32) {
+        let n = self.aovs.len();
+        for y in 0..other.height {
+            for x in 0..other.width {
+                let src = (y * other.width + x) as usize * n;
+                let dst = ((y0 + y) * self.width + x0 + x) as usize * n;
+                for (k, aov) in self.aovs.iter().enumerate() {
+                    if aov.is_id() && self.count[dst + k] > 0 {
+                        continue;
+                    }
+                    self.sum[dst + k] += other.sum[src + k];
+                    self.count[dst + k] += other.count[src + k];
+                }
+            }
+        }
+    }
+    pub fn resolve_aov(&self, aov: Aov) -> Option<HdrImage> {
+        let k = self.aovs.iter().position(|a| *a == aov)?;
+        Some(self.resolve(k))
+    }
+    //第 k 个 AOV 的平均值
+    pub fn resolve(&self, k: usize) -> HdrImage {
+        let n = self.aovs.len();
+        let data = (0..(self.width * self.height) as usize)
+            .map(|p| {
+                let c = self.sum[p * n + k];
+                let scale = 1.0 / self.count[p * n + k].max(1) as f64;
+                [
+                    (c.e.0 * scale) as f32,
+                    (c.e.1 * scale) as f32,
+                    (c.e.2 * scale) as f32,
+                ]
+            })
+            .collect();
+        HdrImage {
+            width: self.width,
+            height: self.height,
+            data,
+        }
+    }
+}
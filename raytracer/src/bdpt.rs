@@ -0,0 +1,651 @@
+use std::f64::{consts::PI, INFINITY};
+
+use crate::{
+    camera::Camera,
+    hittable::{HitRecord, Hittable, HittableList},
+    integrator::{sample_emission, FirstHit, Heuristic, Integrator, PathSample},
+    material::Material,
+    randoms::Rng,
+    ray::Ray,
+    sampler::Sampler,
+    stats,
+    vec3::{mul_vec_dot, Color, Point3, Vec3},
+};
+
+//相机与图像尺寸，光源子路径直接连到相机时用来算落在哪个像素
+pub struct CameraView<'a> {
+    pub cam: &'a Camera,
+    pub width: u32,
+    pub height: u32,
+}
+
+//光源子路径连到相机上得到的贡献，加到像素 (i,j) 上，与像素自己的采样分开累计
+#[derive(Clone, Copy)]
+pub struct Splat {
+    pub i: u32,
+    pub j: u32,
+    pub c: Color,
+}
+
+impl<'a> CameraView<'a> {
+    //p 从镜头上 lens 看去落在图像上的连续坐标（与 TileBuffer::add 的 x,y 相同），以及与视线夹角的余弦
+    fn raster(&self, lens: &Point3, p: &Point3) -> Option<(f64, f64, f64)> {
+        let (s, t, cos) = self.cam.project(lens, p)?;
+        let (w, h) = (self.width as f64, self.height as f64);
+        let x = s * (w - 1.0);
+        let y = h - t * (h - 1.0);
+        if x >= 0.0 && x < w && y > 0.0 && y <= h {
+            Some((x, y, cos))
+        } else {
+            None
+        }
+    }
+    //整幅图在离镜头距离 1 处所占的面积；get_ray 的 s,t 每像素步长为 1/(w-1)，图像覆盖 w/(w-1)
+    fn film_area(&self) -> f64 {
+        let (w, h) = (self.width as f64, self.height as f64);
+        self.cam.unit_area() * w / (w - 1.0) * h / (h - 1.0)
+    }
+    //相机从 lens 朝 dir 方向发出光线的立体角 pdf，落在图像外时为 0
+    fn pdf_dir(&self, lens: &Point3, dir: &Vec3) -> f64 {
+        match self.raster(lens, &(*lens + *dir)) {
+            Some((_, _, cos)) => 1.0 / (self.film_area() * cos * cos * cos),
+            None => 0.0,
+        }
+    }
+}
+
+#[derive(Clone, Copy, PartialEq)]
+enum VertexKind {
+    Camera,
+    Light,
+    Surface,
+}
+
+//子路径上的一个顶点；pdf_fwd 为沿子路径生成方向得到本顶点的面积 pdf，pdf_rev 为反方向的
+#[derive(Clone, Copy)]
+struct Vertex<'a> {
+    kind: VertexKind,
+    p: Point3,
+    //Surface 为朝向来时一侧的法线，Light 为发光一侧的法线
+    n: Vec3,
+    beta: Color,
+    //Light 为发出的辐亮度，Surface 为朝来时方向的自发光（只有相机子路径记录）
+    le: Color,
+    pdf_fwd: f64,
+    pdf_rev: f64,
+    delta: bool,
+    //材质有非镜面的散射，可以与另一条子路径相连
+    connectible: bool,
+    //位于 lights 里的光源上，可以被光源采样得到
+    on_light: bool,
+    mat: Option<&'a dyn Material>,
+    t: f64,
+    front_face: bool,
+    u: f64,
+    v: f64,
+    object_id: u32,
+}
+
+impl<'a> Vertex<'a> {
+    fn endpoint(kind: VertexKind, p: Point3, n: Vec3, beta: Color, le: Color, pdf: f64) -> Self {
+        Self {
+            kind,
+            p,
+            n,
+            beta,
+            le,
+            pdf_fwd: pdf,
+            pdf_rev: 0.0,
+            delta: false,
+            connectible: false,
+            on_light: kind == VertexKind::Light,
+            mat: None,
+            t: 0.0,
+            front_face: true,
+            u: 0.0,
+            v: 0.0,
+            object_id: 0,
+        }
+    }
+    fn surface(rec: &HitRecord<'a>, beta: Color) -> Self {
+        Self {
+            kind: VertexKind::Surface,
+            p: rec.p,
+            n: rec.normal,
+            beta,
+            le: Color::new(),
+            pdf_fwd: 0.0,
+            pdf_rev: 0.0,
+            delta: false,
+            connectible: false,
+            on_light: false,
+            mat: Some(rec.mat_ptr),
+            t: rec.t,
+            front_face: rec.front_face,
+            u: rec.u,
+            v: rec.v,
+            object_id: rec.object_id,
+        }
+    }
+    fn rec(&self, mat: &'a dyn Material) -> HitRecord<'a> {
+        HitRecord {
+            p: self.p,
+            normal: self.n,
+            t: self.t,
+            front_face: self.front_face,
+            mat_ptr: mat,
+            u: self.u,
+            v: self.v,
+            object_id: self.object_id,
+        }
+    }
+    //从 from 到达本顶点再散射向 to：返回 f·|cos| 与材质采样出该方向的立体角 pdf，镜面与无材质时为 0
+    fn eval(&self, from: &Point3, to: &Point3, time: f64) -> (Color, f64) {
+        let mat = match self.mat {
+            Some(m) => m,
+            None => return (Color::new(), 0.0),
+        };
+        let rec = self.rec(mat);
+        let r_in = Ray {
+            orig: *from,
+            dir: self.p - *from,
+            time,
+        };
+        //只用非镜面的散射，用不到在反射与折射之间选择的随机数
+        match mat.scatter(&r_in, &rec, 0.5) {
+            Some(srec) if !srec.is_specular => {
+                let mut out = Ray {
+                    orig: self.p,
+                    dir: *to - self.p,
+                    time,
+                };
+                let pdf = srec.pdf_ptr.as_ref().map_or(0.0, |p| p.value(&out.dir));
+                (
+                    srec.attenuation * mat.scattering_pdf(&r_in, &rec, &mut out),
+                    pdf,
+                )
+            }
+            _ => (Color::new(), 0.0),
+        }
+    }
+    //本顶点上的立体角 pdf 换成 next 处的面积 pdf；相机没有面积，不乘余弦
+    fn convert(&self, pdf_dir: f64, next: &Vertex) -> f64 {
+        let w = next.p - self.p;
+        let d2 = w.length_square();
+        if d2 == 0.0 {
+            return 0.0;
+        }
+        let mut pdf = pdf_dir / d2;
+        if next.kind != VertexKind::Camera {
+            pdf *= mul_vec_dot(next.n, w).abs() / d2.sqrt();
+        }
+        pdf
+    }
+    //把本顶点当作光源按余弦分布发光，到达 next 的面积 pdf
+    fn pdf_light(&self, next: &Vertex) -> f64 {
+        let w = next.p - self.p;
+        let cos = mul_vec_dot(self.n, w) / w.length();
+        if cos.is_nan() || cos <= 0.0 {
+            return 0.0;
+        }
+        self.convert(cos / PI, next)
+    }
+    //从 prev 到达本顶点后散射到 next 的面积 pdf
+    fn pdf(&self, view: &CameraView, prev: Option<&Vertex>, next: &Vertex, time: f64) -> f64 {
+        let pdf_dir = match self.kind {
+            VertexKind::Camera => view.pdf_dir(&self.p, &(next.p - self.p)),
+            VertexKind::Light => return self.pdf_light(next),
+            VertexKind::Surface => match prev {
+                Some(prev) => self.eval(&prev.p, &next.p, time).1,
+                None => 0.0,
+            },
+        };
+        self.convert(pdf_dir, next)
+    }
+}
+
+//两点之间没有遮挡
+fn visible<H: Hittable>(world: &H, a: &Point3, b: &Point3, time: f64, rng: &mut Rng) -> bool {
+    let w = *b - *a;
+    let dist = w.length();
+    let shadow = Ray {
+        orig: *a,
+        dir: w / dist,
+        time,
+    };
+    stats::shadow_ray();
+    world.hit(&shadow, 0.001, dist - 0.001, rng).is_none()
+}
+
+fn is_black(c: &Color) -> bool {
+    c.e.0 <= 0.0 && c.e.1 <= 0.0 && c.e.2 <= 0.0
+}
+
+impl Integrator {
+    //双向路径追踪：分别从相机与光源出发生成子路径，两两相连并按 MIS 加权；
+    //光源子路径直接连到相机的贡献落在别的像素上，放进 splats。heuristic 为 None 时按 balance
+    #[allow(clippy::too_many_arguments)]
+    pub fn bdpt<H: Hittable>(
+        &self,
+        r: &Ray,
+        view: &CameraView,
+        lights: &HittableList,
+        background: Color,
+        world: &H,
+        sampler: &mut dyn Sampler,
+        rng: &mut Rng,
+        splats: &mut Vec<Splat>,
+    ) -> PathSample {
+        let max_depth = self.max_depth.max(0) as usize;
+        let total_area = lights.area();
+        let one = Color { e: (1.0, 1.0, 1.0) };
+        let mut radiance = Color::new();
+
+        let mut camera_path = vec![Vertex::endpoint(
+            VertexKind::Camera,
+            r.orig,
+            Vec3::new(),
+            one,
+            Color::new(),
+            1.0,
+        )];
+        let pdf_dir = view.pdf_dir(&r.orig, &r.dir);
+        //光线逃出场景只能由相机子路径得到，权重为 1
+        if let Some(beta) = self.random_walk(
+            r,
+            one,
+            pdf_dir,
+            world,
+            lights,
+            sampler,
+            rng,
+            max_depth + 1,
+            &mut camera_path,
+            true,
+        ) {
+            radiance += beta * background;
+        }
+
+        let mut light_path = vec![];
+        if total_area > 0.0 {
+            if let Some(e) = sample_emission(lights, world, total_area, r.time, sampler, rng) {
+                let pdf_pos = 1.0 / total_area;
+                let v0 =
+                    Vertex::endpoint(VertexKind::Light, e.p, e.n, e.le / pdf_pos, e.le, pdf_pos);
+                let cos = mul_vec_dot(e.dir.unit_vector(), e.n);
+                let beta = v0.beta * (cos / e.pdf_dir);
+                light_path.push(v0);
+                let ray = Ray {
+                    orig: e.p,
+                    dir: e.dir,
+                    time: r.time,
+                };
+                self.random_walk(
+                    &ray,
+                    beta,
+                    e.pdf_dir,
+                    world,
+                    lights,
+                    sampler,
+                    rng,
+                    max_depth,
+                    &mut light_path,
+                    false,
+                );
+            }
+        }
+
+        for t in 1..=camera_path.len() {
+            //s = 1 为光源采样，不依赖光源子路径
+            let s_max = if lights.objects.is_empty() {
+                light_path.len()
+            } else {
+                light_path.len().max(1)
+            };
+            for s in 0..=s_max {
+                if s + t < 2 || s + t - 2 > max_depth || (s == 1 && t == 1) {
+                    continue;
+                }
+                if t == 1 {
+                    if let Some(splat) =
+                        self.connect_to_camera(&light_path, s, view, world, r.time, sampler, rng)
+                    {
+                        splats.push(splat);
+                    }
+                } else {
+                    radiance += self.connect(
+                        &camera_path,
+                        &light_path,
+                        s,
+                        t,
+                        view,
+                        lights,
+                        world,
+                        total_area,
+                        r.time,
+                        sampler,
+                        rng,
+                    );
+                }
+            }
+        }
+
+        let (emitted, first_hit) = match camera_path.get(1) {
+            Some(v) => {
+                let mat = v.mat.unwrap();
+                let rec = v.rec(mat);
+                (
+                    v.le,
+                    Some(FirstHit {
+                        p: v.p,
+                        shading_normal: v.n,
+                        geometric_normal: if v.front_face { v.n } else { -v.n },
+                        albedo: mat.albedo(&rec),
+                        t: v.t,
+                        u: v.u,
+                        v: v.v,
+                        object_id: v.object_id,
+                        material_id: mat.id(),
+                    }),
+                )
+            }
+            None => (background, None),
+        };
+        PathSample {
+            radiance,
+            emitted,
+            first_hit,
+        }
+    }
+    //从 ray 出发随机游走，最多再加 max_vertices 个顶点；camera 为真时记录击中处的自发光。
+    //光线逃出场景时返回此时的 beta
+    #[allow(clippy::too_many_arguments)]
+    fn random_walk<'a, H: Hittable>(
+        &self,
+        ray: &Ray,
+        beta: Color,
+        pdf_dir: f64,
+        world: &'a H,
+        lights: &HittableList,
+        sampler: &mut dyn Sampler,
+        rng: &mut Rng,
+        max_vertices: usize,
+        path: &mut Vec<Vertex<'a>>,
+        camera: bool,
+    ) -> Option<Color> {
+        let (mut ray, mut beta, mut pdf_fwd) = (*ray, beta, pdf_dir);
+        let mut bounces = 0;
+        while bounces < max_vertices {
+            if camera && bounces == 0 {
+                stats::primary_ray();
+            } else {
+                stats::secondary_ray();
+            }
+            let rec = match world.hit(&ray, 0.001, INFINITY, rng) {
+                Some(rec) => rec,
+                None => return Some(beta),
+            };
+            let n = path.len();
+            let mut v = Vertex::surface(&rec, beta);
+            v.pdf_fwd = path[n - 1].convert(pdf_fwd, &v);
+            if camera {
+                v.le = rec.mat_ptr.emitted(&ray, &rec, rec.u, rec.v, &rec.p);
+                v.on_light = !is_black(&v.le) && Self::hits_light(lights, &ray, rec.t, rng);
+            }
+            let srec = rec.mat_ptr.scatter(&ray, &rec, sampler.get_1d());
+            match &srec {
+                Some(s) if s.is_specular => v.delta = true,
+                Some(_) => v.connectible = true,
+                None => {}
+            }
+            path.push(v);
+            bounces += 1;
+            if bounces >= max_vertices {
+                break;
+            }
+            let srec = srec?;
+            if srec.is_specular {
+                beta = beta * srec.attenuation;
+                pdf_fwd = 0.0;
+                path[n - 1].pdf_rev = 0.0;
+                ray = srec.specular_ray;
+            } else {
+                let bsdf_pdf = srec.pdf_ptr?;
+                let dir = bsdf_pdf.generate(sampler);
+                let pdf = bsdf_pdf.value(&dir);
+                if pdf <= 0.0 {
+                    break;
+                }
+                let mut scattered = Ray {
+                    orig: rec.p,
+                    dir,
+                    time: ray.time,
+                };
+                let f = srec.attenuation * rec.mat_ptr.scattering_pdf(&ray, &rec, &mut scattered);
+                beta = beta * f / pdf;
+                //反方向：从散射方向来、散射回上一个顶点
+                let prev = path[n - 1];
+                let rev = path[n].eval(&(rec.p + dir), &prev.p, ray.time).1;
+                path[n - 1].pdf_rev = path[n].convert(rev, &prev);
+                pdf_fwd = pdf;
+                ray = scattered;
+            }
+            if bounces as i32 >= self.rr_depth {
+                let q = beta.e.0.max(beta.e.1).max(beta.e.2).min(0.95);
+                if q <= 0.0 || sampler.get_1d() >= q {
+                    break;
+                }
+                beta = beta / q;
+            }
+        }
+        None
+    }
+    //相机子路径取前 t 个顶点、光源子路径取前 s 个顶点相连（t >= 2）
+    #[allow(clippy::too_many_arguments)]
+    fn connect<H: Hittable>(
+        &self,
+        camera_path: &[Vertex],
+        light_path: &[Vertex],
+        s: usize,
+        t: usize,
+        view: &CameraView,
+        lights: &HittableList,
+        world: &H,
+        total_area: f64,
+        time: f64,
+        sampler: &mut dyn Sampler,
+        rng: &mut Rng,
+    ) -> Color {
+        let pt = &camera_path[t - 1];
+        let mut sampled = None;
+        let l = if s == 0 {
+            if is_black(&pt.le) {
+                return Color::new();
+            }
+            pt.beta * pt.le
+        } else if s == 1 {
+            if !pt.connectible {
+                return Color::new();
+            }
+            let dir = lights.random(&pt.p, sampler);
+            let pdf = lights.pdf_value(&pt.p, &dir);
+            if pdf <= 0.0 {
+                return Color::new();
+            }
+            let shadow = Ray {
+                orig: pt.p,
+                dir,
+                time,
+            };
+            stats::shadow_ray();
+            let lrec = match world.hit(&shadow, 0.001, INFINITY, rng) {
+                Some(lrec) if Self::hits_light(lights, &shadow, lrec.t, rng) => lrec,
+                _ => return Color::new(),
+            };
+            let le = lrec
+                .mat_ptr
+                .emitted(&shadow, &lrec, lrec.u, lrec.v, &lrec.p);
+            if is_black(&le) {
+                return Color::new();
+            }
+            let f = pt.eval(&camera_path[t - 2].p, &lrec.p, time).0;
+            let pdf_pos = if total_area > 0.0 {
+                1.0 / total_area
+            } else {
+                0.0
+            };
+            sampled = Some(Vertex::endpoint(
+                VertexKind::Light,
+                lrec.p,
+                lrec.normal,
+                le / pdf_pos,
+                le,
+                pdf_pos,
+            ));
+            pt.beta * f * le / pdf
+        } else {
+            let qs = &light_path[s - 1];
+            if !qs.connectible || !pt.connectible {
+                return Color::new();
+            }
+            let fq = qs.eval(&light_path[s - 2].p, &pt.p, time).0;
+            let fp = pt.eval(&camera_path[t - 2].p, &qs.p, time).0;
+            let l = qs.beta * fq * fp * pt.beta / (qs.p - pt.p).length_square();
+            if is_black(&l) || !visible(world, &pt.p, &qs.p, time, rng) {
+                return Color::new();
+            }
+            l
+        };
+        if is_black(&l) {
+            return l;
+        }
+        l * self.bdpt_weight(
+            view,
+            camera_path,
+            light_path,
+            s,
+            t,
+            sampled,
+            total_area,
+            time,
+        )
+    }
+    //光源子路径的第 s 个顶点连到镜头上新取的一点（t = 1），s >= 2
+    #[allow(clippy::too_many_arguments)]
+    fn connect_to_camera<H: Hittable>(
+        &self,
+        light_path: &[Vertex],
+        s: usize,
+        view: &CameraView,
+        world: &H,
+        time: f64,
+        sampler: &mut dyn Sampler,
+        rng: &mut Rng,
+    ) -> Option<Splat> {
+        let qs = &light_path[s - 1];
+        if !qs.connectible {
+            return None;
+        }
+        let lens = view.cam.sample_lens(sampler);
+        let (x, y, cos) = view.raster(&lens, &qs.p)?;
+        let d2 = (lens - qs.p).length_square();
+        let fq = qs.eval(&light_path[s - 2].p, &lens, time).0;
+        let l = qs.beta * fq / (view.film_area() * cos * cos * cos * d2);
+        if is_black(&l) || !visible(world, &qs.p, &lens, time, rng) {
+            return None;
+        }
+        let camera = Vertex::endpoint(
+            VertexKind::Camera,
+            lens,
+            Vec3::new(),
+            Color { e: (1.0, 1.0, 1.0) },
+            Color::new(),
+            1.0,
+        );
+        let c = l * self.bdpt_weight(view, &[], light_path, s, 1, Some(camera), 0.0, time);
+        Some(Splat {
+            i: (x.floor() as u32).min(view.width - 1),
+            j: (y.ceil() as u32).clamp(1, view.height) - 1,
+            c,
+        })
+    }
+    //s,t 这种连法的 MIS 权重：把同一条路径换成其他连法时的 pdf 与本连法的比值累加（pbrt 的做法）；
+    //sampled 为本次连接新取的端点，取代 s = 1 的光源顶点或 t = 1 的相机顶点
+    #[allow(clippy::too_many_arguments)]
+    fn bdpt_weight(
+        &self,
+        view: &CameraView,
+        camera_path: &[Vertex],
+        light_path: &[Vertex],
+        s: usize,
+        t: usize,
+        sampled: Option<Vertex>,
+        total_area: f64,
+        time: f64,
+    ) -> f64 {
+        if s + t == 2 {
+            return 1.0;
+        }
+        //不在 lights 里的发光物体只能被相机子路径击中
+        if s == 0 && !camera_path[t - 1].on_light {
+            return 1.0;
+        }
+        let mut cam: Vec<Vertex> = if t == 1 {
+            sampled.into_iter().collect()
+        } else {
+            camera_path[..t].to_vec()
+        };
+        let mut light: Vec<Vertex> = if s == 1 {
+            sampled.into_iter().collect()
+        } else {
+            light_path[..s].to_vec()
+        };
+        cam[t - 1].delta = false;
+        if s > 0 {
+            light[s - 1].delta = false;
+        }
+        let pt = cam[t - 1];
+        let pt_minus = if t > 1 { Some(cam[t - 2]) } else { None };
+        let qs = if s > 0 { Some(light[s - 1]) } else { None };
+        let qs_minus = if s > 1 { Some(light[s - 2]) } else { None };
+        cam[t - 1].pdf_rev = match &qs {
+            Some(qs) => qs.pdf(view, qs_minus.as_ref(), &pt, time),
+            None if pt.on_light && total_area > 0.0 => 1.0 / total_area,
+            None => 0.0,
+        };
+        if let Some(pm) = &pt_minus {
+            cam[t - 2].pdf_rev = match &qs {
+                Some(qs) => pt.pdf(view, Some(qs), pm, time),
+                None => pt.pdf_light(pm),
+            };
+        }
+        if let Some(qs) = &qs {
+            light[s - 1].pdf_rev = pt.pdf(view, pt_minus.as_ref(), qs, time);
+        }
+        if let (Some(qs), Some(qm)) = (&qs, &qs_minus) {
+            light[s - 2].pdf_rev = qs.pdf(view, Some(&pt), qm, time);
+        }
+        let remap = |f: f64| if f != 0.0 { f } else { 1.0 };
+        let h = |r: f64| match self.heuristic {
+            Some(Heuristic::Power) => r * r,
+            _ => r,
+        };
+        let mut sum = 0.0;
+        let mut ri = 1.0;
+        for i in (1..t).rev() {
+            ri *= remap(cam[i].pdf_rev) / remap(cam[i].pdf_fwd);
+            if !cam[i].delta && !cam[i - 1].delta {
+                sum += h(ri);
+            }
+        }
+        ri = 1.0;
+        for i in (0..s).rev() {
+            ri *= remap(light[i].pdf_rev) / remap(light[i].pdf_fwd);
+            let delta_prev = i > 0 && light[i - 1].delta;
+            if !light[i].delta && !delta_prev {
+                sum += h(ri);
+            }
+        }
+        1.0 / (1.0 + sum)
+    }
+}
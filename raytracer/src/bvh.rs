@@ -1,58 +1,354 @@
-use std::cmp::Ordering;
+use std::{
+    sync::atomic::{AtomicU64, Ordering},
+    thread,
+    time::Instant,
+};
 
 use crate::{
     aabb::{surrounding_box, AABB},
     hittable::{HitRecord, Hittable},
-    randoms::random_int,
+    randoms::Rng,
     ray::Ray,
+    stats,
     vec3::Vec3,
 };
+
+//SAH 里遍历一个节点与求交一个图元的相对开销
+const TRAVERSAL_COST: f64 = 1.0;
+const INTERSECT_COST: f64 = 1.0;
+//每个轴上按图元中心分的桶数，只在桶的边界上考虑分割
+const BINS: usize = 16;
+//图元不超过这个数时，分割不比全部求交划算就停下成为叶节点
+const MAX_LEAF_SIZE: usize = 4;
+//refit 后的 SAH 代价超过建树时的这么多倍就该重建
+const REFIT_LIMIT: f64 = 1.5;
+
+//一个节点：叶节点的图元是 indices[offset..offset+count]；
+//内部节点的 count 为 0，第一个子节点紧跟在它后面，第二个子节点在 offset 处，axis 为分割轴
+#[derive(Clone, Copy)]
+struct LinearNode {
+    bbox: AABB,
+    offset: u32,
+    count: u32,
+    axis: u8,
+}
+
+//节点按深度优先顺序存在一个数组里，图元的下标另存一个数组，物体本身保持原来的顺序；
+//built_cost 为建树时的 SAH 代价，refit 之后与它比较决定是否重建
 pub struct BvhNode {
-    pub left: Option<Box<dyn Hittable>>,
-    pub right: Option<Box<dyn Hittable>>,
-    pub box0: AABB,
+    nodes: Vec<LinearNode>,
+    indices: Vec<u32>,
+    objects: Vec<Box<dyn Hittable>>,
+    built_cost: f64,
 }
 impl Hittable for BvhNode {
-    fn hit(&self, r: &Ray, t_min: f64, t_max: f64) -> Option<HitRecord> {
-        if !(self.box0.hit(r, t_min, t_max)) {
+    fn hit(&self, r: &Ray, t_min: f64, t_max: f64, rng: &mut Rng) -> Option<HitRecord> {
+        if self.nodes.is_empty() {
             return None;
         }
-        let mut rec = HitRecord::new();
-        let hit_left = if self.left.is_some() {
-            self.left.as_ref().unwrap().hit(r, t_min, t_max)
-        } else {
-            None
-        };
-        let mut hit_any = false;
-        if let Some(..) = hit_left {
-            hit_any = true;
-            rec = hit_left.unwrap();
-        }
-        let hit_right = if self.right.is_some() {
-            self.right
-                .as_ref()
+        let orig = [r.orig.e.0, r.orig.e.1, r.orig.e.2];
+        let inv_dir = [1.0 / r.dir.e.0, 1.0 / r.dir.e.1, 1.0 / r.dir.e.2];
+        let dir_is_neg = [inv_dir[0] < 0.0, inv_dir[1] < 0.0, inv_dir[2] < 0.0];
+        let mut closest = t_max;
+        let mut rec = None;
+        //待访问的远侧子节点
+        let mut stack = [0u32; STACK_SIZE];
+        let mut top = 0;
+        let mut current = 0;
+        loop {
+            stats::node_visit();
+            let node = &self.nodes[current];
+            if node.bbox.hit_inv(&orig, &inv_dir, t_min, closest) {
+                if node.count > 0 {
+                    let first = node.offset as usize;
+                    for &i in &self.indices[first..first + node.count as usize] {
+                        if let Some(h) = self.objects[i as usize].hit(r, t_min, closest, rng) {
+                            closest = h.t;
+                            rec = Some(h);
+                        }
+                    }
+                } else {
+                    //沿光线方向先访问近的一侧，远的一侧入栈
+                    if dir_is_neg[node.axis as usize] {
+                        stack[top] = current as u32 + 1;
+                        current = node.offset as usize;
+                    } else {
+                        stack[top] = node.offset;
+                        current += 1;
+                    }
+                    top += 1;
+                    continue;
+                }
+            }
+            if top == 0 {
+                break;
+            }
+            top -= 1;
+            current = stack[top] as usize;
+        }
+        rec
+    }
+    fn bounding_box(&self, _time0: f64, _time1: f64, output_box: &mut AABB) -> bool {
+        match self.nodes.first() {
+            Some(root) => {
+                *output_box = root.bbox;
+                true
+            }
+            None => false,
+        }
+    }
+}
+
+//建树时每个图元的包围盒与中心，index 为它在原列表中的位置
+struct BuildPrim {
+    bbox: AABB,
+    centroid: [f64; 3],
+    index: usize,
+}
+
+fn merge(a: Option<AABB>, b: &AABB) -> Option<AABB> {
+    Some(match a {
+        Some(a) => surrounding_box(&a, b),
+        None => *b,
+    })
+}
+
+fn bin_of(c: f64, lo: f64, extent: f64) -> usize {
+    (((c - lo) / extent * BINS as f64) as usize).min(BINS - 1)
+}
+
+//把满足 pred 的图元移到前面，返回它们的个数
+fn partition(prims: &mut [BuildPrim], pred: impl Fn(&BuildPrim) -> bool) -> usize {
+    let mut mid = 0;
+    for k in 0..prims.len() {
+        if pred(&prims[k]) {
+            prims.swap(mid, k);
+            mid += 1;
+        }
+    }
+    mid
+}
+
+//在三个轴上分桶，用前缀与后缀扫描求出每个桶边界处分割的 SAH 代价，返回最小者 (代价, 轴, 左侧最后一个桶)
+fn best_split(
+    prims: &[BuildPrim],
+    lo: &[f64; 3],
+    hi: &[f64; 3],
+    area: f64,
+) -> Option<(f64, usize, usize)> {
+    let mut best: Option<(f64, usize, usize)> = None;
+    for axis in 0..3 {
+        let extent = hi[axis] - lo[axis];
+        if extent <= 0.0 {
+            continue;
+        }
+        let mut count = [0usize; BINS];
+        let mut bounds: [Option<AABB>; BINS] = [None; BINS];
+        for p in prims {
+            let b = bin_of(p.centroid[axis], lo[axis], extent);
+            count[b] += 1;
+            bounds[b] = merge(bounds[b], &p.bbox);
+        }
+        let mut right_area = [0.0; BINS];
+        let mut right_count = [0usize; BINS];
+        let (mut acc, mut n) = (None, 0);
+        for b in (1..BINS).rev() {
+            if let Some(bb) = &bounds[b] {
+                acc = merge(acc, bb);
+            }
+            n += count[b];
+            right_area[b] = acc.map_or(0.0, |a| a.surface_area());
+            right_count[b] = n;
+        }
+        let (mut acc, mut n) = (None, 0);
+        for b in 0..BINS - 1 {
+            if let Some(bb) = &bounds[b] {
+                acc = merge(acc, bb);
+            }
+            n += count[b];
+            if n == 0 || right_count[b + 1] == 0 {
+                continue;
+            }
+            let left_area = acc.map_or(0.0, |a| a.surface_area());
+            let cost = TRAVERSAL_COST
+                + INTERSECT_COST
+                    * (n as f64 * left_area + right_count[b + 1] as f64 * right_area[b + 1])
+                    / area;
+            if !matches!(best, Some((c, _, _)) if c <= cost) {
+                best = Some((cost, axis, b));
+            }
+        }
+    }
+    best
+}
+
+//深度超过 MEDIAN_DEPTH 后改为沿最长轴按个数对半分，保证树深不超过遍历栈的大小
+const MEDIAN_DEPTH: usize = 24;
+const STACK_SIZE: usize = 64;
+//图元少于这个数的子树不再分给新线程
+const PARALLEL_MIN: usize = 4096;
+
+fn object_box(o: &dyn Hittable, time0: f64, time1: f64) -> AABB {
+    let mut bbox = AABB {
+        minimum: Vec3::new(),
+        maximum: Vec3::new(),
+    };
+    o.bounding_box(time0, time1, &mut bbox);
+    bbox
+}
+
+//一个节点的包围盒与分割方式：None 为叶节点，否则为 (左侧的图元数, 分割轴)，prims 已按分割重排
+fn split_node(prims: &mut [BuildPrim], depth: usize) -> (AABB, Option<(usize, usize)>) {
+    let mut bbox = None;
+    let (mut lo, mut hi) = ([f64::INFINITY; 3], [f64::NEG_INFINITY; 3]);
+    for p in prims.iter() {
+        bbox = merge(bbox, &p.bbox);
+        for a in 0..3 {
+            lo[a] = lo[a].min(p.centroid[a]);
+            hi[a] = hi[a].max(p.centroid[a]);
+        }
+    }
+    let box0 = bbox.unwrap_or(AABB {
+        minimum: Vec3::new(),
+        maximum: Vec3::new(),
+    });
+    let n = prims.len();
+    let longest = (0..3)
+        .max_by(|&a, &b| (hi[a] - lo[a]).partial_cmp(&(hi[b] - lo[b])).unwrap())
+        .unwrap();
+    let split = if n <= 1 {
+        None
+    } else if depth >= MEDIAN_DEPTH {
+        prims.select_nth_unstable_by(n / 2, |a, b| {
+            a.centroid[longest]
+                .partial_cmp(&b.centroid[longest])
                 .unwrap()
-                .hit(r, t_min, if hit_any { rec.t } else { t_max })
-        } else {
-            None
-        };
+        });
+        Some((n / 2, longest))
+    } else {
+        match best_split(prims, &lo, &hi, box0.surface_area()) {
+            Some((cost, axis, b)) if n > MAX_LEAF_SIZE || cost < INTERSECT_COST * n as f64 => {
+                let extent = hi[axis] - lo[axis];
+                let mid = partition(prims, |p| bin_of(p.centroid[axis], lo[axis], extent) <= b);
+                Some((mid, axis))
+            }
+            Some(_) => None,
+            //中心全部重合时分桶无从下手，图元太多就按个数对半分
+            None if n > MAX_LEAF_SIZE => Some((n / 2, longest)),
+            None => None,
+        }
+    };
+    (box0, split)
+}
 
-        if let Some(..) = hit_right {
-            hit_any = true;
-            rec = hit_right.unwrap()
-        };
-        if hit_any {
-            Some(rec)
-        } else {
-            None
+//深度优先建树，节点与叶节点的图元下标依次追加到 nodes 与 indices 的末尾
+fn build(
+    prims: &mut [BuildPrim],
+    depth: usize,
+    nodes: &mut Vec<LinearNode>,
+    indices: &mut Vec<u32>,
+) {
+    let (box0, split) = split_node(prims, depth);
+    match split {
+        Some((mid, axis)) => {
+            let me = nodes.len();
+            nodes.push(LinearNode {
+                bbox: box0,
+                offset: 0,
+                count: 0,
+                axis: axis as u8,
+            });
+            let (left, right) = prims.split_at_mut(mid);
+            build(left, depth + 1, nodes, indices);
+            nodes[me].offset = nodes.len() as u32;
+            build(right, depth + 1, nodes, indices);
+        }
+        None => {
+            nodes.push(LinearNode {
+                bbox: box0,
+                offset: indices.len() as u32,
+                count: prims.len() as u32,
+                axis: 0,
+            });
+            indices.extend(prims.iter().map(|p| p.index as u32));
         }
     }
-    fn bounding_box(&self, _time0: f64, _time1: f64, output_box: &mut AABB) -> bool {
-        *output_box = self.box0;
-        true
+}
+
+//把另建的子树接到 nodes 与 indices 末尾，子树里的偏移量都是相对它自己的数组
+fn append(
+    nodes: &mut Vec<LinearNode>,
+    indices: &mut Vec<u32>,
+    sub_nodes: Vec<LinearNode>,
+    sub_indices: Vec<u32>,
+) {
+    let (base, index_base) = (nodes.len() as u32, indices.len() as u32);
+    nodes.extend(sub_nodes.into_iter().map(|mut node| {
+        node.offset += if node.count > 0 { index_base } else { base };
+        node
+    }));
+    indices.extend(sub_indices);
+}
+
+//图元多于 PARALLEL_MIN 且还有空闲线程时，右子树交给新线程去建，结果与单线程建的树完全相同
+fn build_parallel(
+    mut prims: Vec<BuildPrim>,
+    depth: usize,
+    threads: usize,
+) -> (Vec<LinearNode>, Vec<u32>) {
+    let (mut nodes, mut indices) = (vec![], vec![]);
+    if threads <= 1 || prims.len() < PARALLEL_MIN {
+        build(&mut prims, depth, &mut nodes, &mut indices);
+        return (nodes, indices);
     }
+    let (box0, split) = split_node(&mut prims, depth);
+    let (mid, axis) = match split {
+        Some(s) => s,
+        None => {
+            build(&mut prims, depth, &mut nodes, &mut indices);
+            return (nodes, indices);
+        }
+    };
+    let right = prims.split_off(mid);
+    let right_threads = threads / 2;
+    let handle = thread::spawn(move || build_parallel(right, depth + 1, right_threads));
+    let (left_nodes, left_indices) = build_parallel(prims, depth + 1, threads - right_threads);
+    let (right_nodes, right_indices) = handle.join().unwrap();
+    nodes.push(LinearNode {
+        bbox: box0,
+        offset: 0,
+        count: 0,
+        axis: axis as u8,
+    });
+    append(&mut nodes, &mut indices, left_nodes, left_indices);
+    nodes[0].offset = nodes.len() as u32;
+    append(&mut nodes, &mut indices, right_nodes, right_indices);
+    (nodes, indices)
+}
+
+//场景准备时可用的线程数
+pub fn available_threads() -> usize {
+    thread::available_parallelism().map_or(1, |n| n.get())
+}
+
+//到目前为止建 BVH 所用的总时间，以纳秒计
+static BUILD_NANOS: AtomicU64 = AtomicU64::new(0);
+pub fn build_seconds() -> f64 {
+    BUILD_NANOS.load(Ordering::Relaxed) as f64 * 1e-9
 }
+
+//树的质量：SAH 代价按根节点面积归一化，越小越好
+pub struct BvhQuality {
+    pub nodes: usize,
+    pub leaves: usize,
+    pub primitives: usize,
+    pub max_depth: usize,
+    pub max_leaf_size: usize,
+    pub sah_cost: f64,
+}
+
 impl BvhNode {
+    //把 [start, end) 内的物体从 src_objects 中移出，按 SAH 分桶建树
     pub fn new_nodes(
         src_objects: &mut Vec<Box<dyn Hittable>>,
         start: u32,
@@ -60,132 +356,313 @@ impl BvhNode {
         time0: f64,
         time1: f64,
     ) -> Self {
-        let left_: Option<Box<dyn Hittable>>;
-        let right_: Option<Box<dyn Hittable>>;
-        let objects = src_objects;
-        let axis = random_int(0, 2);
-        let comparator = if axis == 0 {
-            box_x_compare
-        } else if axis == 1 {
-            box_y_compare
+        let timer = Instant::now();
+        let objects: Vec<Box<dyn Hittable>> =
+            src_objects.drain(start as usize..end as usize).collect();
+        let prims: Vec<BuildPrim> = objects
+            .iter()
+            .enumerate()
+            .map(|(index, o)| {
+                let bbox = object_box(o.as_ref(), time0, time1);
+                let c = bbox.centroid();
+                BuildPrim {
+                    bbox,
+                    centroid: [c.e.0, c.e.1, c.e.2],
+                    index,
+                }
+            })
+            .collect();
+        let (nodes, indices) = if prims.is_empty() {
+            (vec![], vec![])
         } else {
-            box_z_compare
+            build_parallel(prims, 0, available_threads())
         };
-
-        let object_span = end - start;
-        if object_span == 1 {
-            left_ = Some(objects.remove(start as usize));
-            right_ = None;
-        } else if object_span == 2 {
-            if comparator(&objects[start as usize], &objects[(start + 1) as usize])
-                == Ordering::Less
-            {
-                right_ = Some(objects.remove((start + 1) as usize));
-                left_ = Some(objects.remove(start as usize));
+        let mut bvh = Self {
+            nodes,
+            indices,
+            objects,
+            built_cost: 0.0,
+        };
+        bvh.built_cost = bvh.quality().sah_cost;
+        BUILD_NANOS.fetch_add(timer.elapsed().as_nanos() as u64, Ordering::Relaxed);
+        bvh
+    }
+    pub fn len(&self) -> usize {
+        self.objects.len()
+    }
+    pub fn is_empty(&self) -> bool {
+        self.objects.is_empty()
+    }
+    pub fn into_objects(self) -> Vec<Box<dyn Hittable>> {
+        self.objects
+    }
+    //换上同样个数、同样顺序的物体（如动画的下一帧），拓扑不变，需要再 refit；个数不同时原样退回
+    pub fn set_objects(
+        &mut self,
+        objects: Vec<Box<dyn Hittable>>,
+    ) -> Result<(), Vec<Box<dyn Hittable>>> {
+        if objects.len() != self.objects.len() {
+            return Err(objects);
+        }
+        self.objects = objects;
+        Ok(())
+    }
+    //保持树的拓扑，自底向上重算包围盒：子节点都排在父节点之后，倒序扫一遍即可
+    pub fn refit(&mut self, time0: f64, time1: f64) {
+        let timer = Instant::now();
+        for k in (0..self.nodes.len()).rev() {
+            let node = self.nodes[k];
+            let bbox = if node.count > 0 {
+                let first = node.offset as usize;
+                self.indices[first..first + node.count as usize]
+                    .iter()
+                    .fold(None, |b, &i| {
+                        merge(
+                            b,
+                            &object_box(self.objects[i as usize].as_ref(), time0, time1),
+                        )
+                    })
             } else {
-                left_ = Some(objects.remove((start + 1) as usize));
-                right_ = Some(objects.remove(start as usize));
-            }
-        } else {
-            let objects_m = &mut objects[start as usize..end as usize];
-            objects_m.sort_by(comparator);
-
-            let mid = start + object_span / 2;
-            right_ = Some(Box::new(BvhNode::new_nodes(
-                objects, mid, end, time0, time1,
-            )));
-            left_ = Some(Box::new(BvhNode::new_nodes(
-                objects, start, mid, time0, time1,
-            )));
-        }
-        let mut box_left: AABB = AABB {
-            minimum: Vec3::new(),
-            maximum: Vec3::new(),
+                merge(
+                    Some(self.nodes[k + 1].bbox),
+                    &self.nodes[node.offset as usize].bbox,
+                )
+            };
+            self.nodes[k].bbox = bbox.unwrap();
+        }
+        BUILD_NANOS.fetch_add(timer.elapsed().as_nanos() as u64, Ordering::Relaxed);
+    }
+    //物体移动后旧的划分可能很差：SAH 代价比建树时涨了 REFIT_LIMIT 倍以上就该重建
+    pub fn needs_rebuild(&self) -> bool {
+        self.quality().sah_cost > REFIT_LIMIT * self.built_cost
+    }
+    pub fn built_cost(&self) -> f64 {
+        self.built_cost
+    }
+    pub fn quality(&self) -> BvhQuality {
+        let mut q = BvhQuality {
+            nodes: self.nodes.len(),
+            leaves: 0,
+            primitives: 0,
+            max_depth: 0,
+            max_leaf_size: 0,
+            sah_cost: 0.0,
         };
-        let mut box_right: AABB = AABB {
-            minimum: Vec3::new(),
-            maximum: Vec3::new(),
+        let root_area = match self.nodes.first() {
+            Some(root) => root.bbox.surface_area(),
+            None => return q,
         };
-        left_
-            .as_ref()
-            .unwrap()
-            .bounding_box(time0, time1, &mut box_left);
-        if right_.is_some() {
-            right_
-                .as_ref()
-                .unwrap()
-                .bounding_box(time0, time1, &mut box_right)
-        } else {
-            box_right = box_left;
-            false
-        };
-        let box0_: AABB = surrounding_box(&box_left, &box_right);
-        Self {
-            left: left_,
-            right: right_,
-            box0: box0_,
+        //深度优先顺序里每个节点的深度
+        let mut stack = vec![(0, 0)];
+        while let Some((k, depth)) = stack.pop() {
+            let node = &self.nodes[k];
+            let weight = if root_area > 0.0 {
+                node.bbox.surface_area() / root_area
+            } else {
+                1.0
+            };
+            q.max_depth = q.max_depth.max(depth);
+            if node.count > 0 {
+                let n = node.count as usize;
+                q.leaves += 1;
+                q.primitives += n;
+                q.max_leaf_size = q.max_leaf_size.max(n);
+                q.sah_cost += weight * INTERSECT_COST * n as f64;
+            } else {
+                q.sah_cost += weight * TRAVERSAL_COST;
+                stack.push((k + 1, depth + 1));
+                stack.push((node.offset as usize, depth + 1));
+            }
         }
+        q
     }
 }
-#[allow(clippy::borrowed_box)]
-pub fn box_x_compare(a: &Box<dyn Hittable>, b: &Box<dyn Hittable>) -> Ordering {
-    let mut box_a = AABB {
-        minimum: Vec3::new(),
-        maximum: Vec3::new(),
-    };
-    let mut box_b = AABB {
-        minimum: Vec3::new(),
-        maximum: Vec3::new(),
+unsafe impl Send for BvhNode {}
+unsafe impl Sync for BvhNode {}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::hittable::{
+        mybox::MyBox, rotate::RotateY, sphere::Sphere, translate::Translate, HittableList,
     };
-    a.bounding_box(0.0, 0.0, &mut box_a);
-    b.bounding_box(0.0, 0.0, &mut box_b);
-    if box_a.minimum.e.0 < box_b.minimum.e.0 {
-        Ordering::Less
-    } else if box_a.minimum.e.0 > box_b.minimum.e.0 {
-        Ordering::Greater
-    } else {
-        Ordering::Equal
+    use crate::material::dielectric::Dielectric;
+
+    //大小不一、互相重叠的球和盒子，同一个种子得到同样的一组物体
+    fn scene(seed: u64) -> Vec<Box<dyn Hittable>> {
+        let mut rng = Rng::new(seed);
+        let mut objects: Vec<Box<dyn Hittable>> = Vec::new();
+        for k in 0..400 {
+            let center = rng.random_vec(-10.0, 10.0);
+            if k % 4 == 0 {
+                let half = rng.random_vec(0.05, 1.0);
+                objects.push(Box::new(MyBox::new(
+                    &(center - half),
+                    &(center + half),
+                    Dielectric::new(1.5),
+                )));
+            } else {
+                objects.push(Box::new(Sphere {
+                    center,
+                    radius: rng.random_double(0.05, 1.5),
+                    mat_ptr: Dielectric::new(1.5),
+                }));
+            }
+        }
+        objects
     }
-}
-#[allow(clippy::borrowed_box)]
-pub fn box_y_compare(a: &Box<dyn Hittable>, b: &Box<dyn Hittable>) -> Ordering {
-    let mut box_a = AABB {
-        minimum: Vec3::new(),
-        maximum: Vec3::new(),
-    };
-    let mut box_b = AABB {
-        minimum: Vec3::new(),
-        maximum: Vec3::new(),
-    };
-    a.bounding_box(0.0, 0.0, &mut box_a);
-    b.bounding_box(0.0, 0.0, &mut box_b);
-    if box_a.minimum.e.1 < box_b.minimum.e.1 {
-        Ordering::Less
-    } else if box_a.minimum.e.1 > box_b.minimum.e.1 {
-        Ordering::Greater
-    } else {
-        Ordering::Equal
+
+    #[test]
+    fn sah_bvh_agrees_with_brute_force() {
+        let mut objects = scene(5);
+        let end = objects.len() as u32;
+        let bvh = BvhNode::new_nodes(&mut objects, 0, end, 0.0, 1.0);
+        let list = HittableList { objects: scene(5) };
+        let q = bvh.quality();
+        assert_eq!(q.primitives, 400);
+        assert!(q.max_leaf_size <= MAX_LEAF_SIZE);
+        let mut rng = Rng::new(9);
+        let mut hits = 0;
+        for _ in 0..5000 {
+            //从外面朝物体堆里随机一点射
+            let orig = rng.random_vec(-15.0, 15.0);
+            let r = Ray {
+                orig,
+                dir: rng.random_vec(-8.0, 8.0) - orig,
+                time: 0.0,
+            };
+            let a = bvh.hit(&r, 0.001, f64::INFINITY, &mut rng);
+            let b = list.hit(&r, 0.001, f64::INFINITY, &mut rng);
+            match (a, b) {
+                (None, None) => {}
+                (Some(a), Some(b)) => {
+                    hits += 1;
+                    assert_eq!(a.t, b.t);
+                    assert_eq!(a.p.e, b.p.e);
+                    assert_eq!(a.normal.e, b.normal.e);
+                }
+                _ => panic!("bvh and list disagree on {:?} {:?}", r.orig.e, r.dir.e),
+            }
+        }
+        //大部分光线都应该打到东西，否则这个比较说明不了什么
+        assert!(hits > 2500, "only {} hits", hits);
     }
-}
-#[allow(clippy::borrowed_box)]
-pub fn box_z_compare(a: &Box<dyn Hittable>, b: &Box<dyn Hittable>) -> Ordering {
-    let mut box_a = AABB {
-        minimum: Vec3::new(),
-        maximum: Vec3::new(),
-    };
-    let mut box_b = AABB {
-        minimum: Vec3::new(),
-        maximum: Vec3::new(),
-    };
-    a.bounding_box(0.0, 0.0, &mut box_a);
-    b.bounding_box(0.0, 0.0, &mut box_b);
-    if box_a.minimum.e.2 < box_b.minimum.e.2 {
-        Ordering::Less
-    } else if box_a.minimum.e.2 > box_b.minimum.e.2 {
-        Ordering::Greater
-    } else {
-        Ordering::Equal
+
+    #[test]
+    fn flat_traversal_agrees_with_brute_force_on_axis_rays() {
+        //沿坐标轴的光线方向分量为 0，倒数为无穷；起点在物体堆里面，t_max 也有限
+        let mut objects = scene(11);
+        let end = objects.len() as u32;
+        let bvh = BvhNode::new_nodes(&mut objects, 0, end, 0.0, 1.0);
+        let list = HittableList { objects: scene(11) };
+        let mut rng = Rng::new(3);
+        for k in 0..6000 {
+            let mut dir = Vec3::new();
+            let sign = if k % 2 == 0 { 1.0 } else { -1.0 };
+            match (k / 2) % 3 {
+                0 => dir.e.0 = sign,
+                1 => dir.e.1 = sign,
+                _ => dir.e.2 = sign,
+            }
+            let r = Ray {
+                orig: rng.random_vec(-10.0, 10.0),
+                dir,
+                time: 0.0,
+            };
+            let t_max = rng.random_double(0.5, 20.0);
+            let a = bvh.hit(&r, 0.001, t_max, &mut rng).map(|h| h.t);
+            let b = list.hit(&r, 0.001, t_max, &mut rng).map(|h| h.t);
+            assert_eq!(a, b, "{:?} {:?} up to {}", r.orig.e, r.dir.e, t_max);
+        }
+    }
+
+    #[test]
+    fn flat_bvh_keeps_objects_with_equal_centroids() {
+        //图元中心全都一样时无法分割，所有物体都要留在树里
+        let mut objects: Vec<Box<dyn Hittable>> = (0..50)
+            .map(|_| {
+                Box::new(Sphere {
+                    center: Vec3::new(),
+                    radius: 1.0,
+                    mat_ptr: Dielectric::new(1.5),
+                }) as Box<dyn Hittable>
+            })
+            .collect();
+        let bvh = BvhNode::new_nodes(&mut objects, 0, 50, 0.0, 1.0);
+        assert_eq!(bvh.quality().primitives, 50);
+        let r = Ray {
+            orig: Vec3 {
+                e: (0.0, 0.0, -5.0),
+            },
+            dir: Vec3 { e: (0.0, 0.0, 1.0) },
+            time: 0.0,
+        };
+        let h = bvh.hit(&r, 0.001, f64::INFINITY, &mut Rng::new(0)).unwrap();
+        assert!((h.t - 4.0).abs() < 1e-9);
+    }
+
+    //动画的第 frame 帧：绕 y 轴转动并平移的盒子，个数与顺序每帧都一样
+    fn frame_objects(frame: f64) -> Vec<Box<dyn Hittable>> {
+        let mut rng = Rng::new(21);
+        (0..120)
+            .map(|_| {
+                let half = rng.random_vec(0.2, 1.5);
+                let start = rng.random_vec(-10.0, 10.0);
+                let velocity = rng.random_vec(-3.0, 3.0);
+                let angle = rng.random_double(0.0, 360.0);
+                let spin = rng.random_double(-90.0, 90.0);
+                Box::new(Translate {
+                    offset: start + velocity * frame,
+                    ptr: RotateY::new(
+                        MyBox::new(&-half, &half, Dielectric::new(1.5)),
+                        angle + spin * frame,
+                    ),
+                }) as Box<dyn Hittable>
+            })
+            .collect()
+    }
+
+    #[test]
+    fn refit_matches_a_fresh_build() {
+        let mut objects = frame_objects(0.0);
+        let mut refitted = BvhNode::new_nodes(&mut objects, 0, 120, 0.0, 1.0);
+        for frame in 1..4 {
+            let frame = frame as f64;
+            assert!(refitted.set_objects(frame_objects(frame)).is_ok());
+            refitted.refit(0.0, 1.0);
+            let mut objects = frame_objects(frame);
+            let fresh = BvhNode::new_nodes(&mut objects, 0, 120, 0.0, 1.0);
+            //根节点的包围盒只取决于物体，与拓扑无关
+            let empty = AABB {
+                minimum: Vec3::new(),
+                maximum: Vec3::new(),
+            };
+            let (mut a, mut b) = (empty, empty);
+            assert!(refitted.bounding_box(0.0, 1.0, &mut a));
+            assert!(fresh.bounding_box(0.0, 1.0, &mut b));
+            assert_eq!(a.minimum.e, b.minimum.e);
+            assert_eq!(a.maximum.e, b.maximum.e);
+            //每个节点的包围盒都要装得下它下面的物体，否则两棵树会漏掉物体；
+            //与逐个求交比较，物体自己的包围盒算错了也会在这里暴露出来
+            let list = HittableList {
+                objects: frame_objects(frame),
+            };
+            let mut rng = Rng::new(frame as u64);
+            for _ in 0..4000 {
+                let orig = rng.random_vec(-20.0, 20.0);
+                let r = Ray {
+                    orig,
+                    dir: rng.random_vec(-10.0, 10.0) - orig,
+                    time: 0.0,
+                };
+                let expected = list.hit(&r, 0.001, f64::INFINITY, &mut rng).map(|h| h.t);
+                let got = refitted
+                    .hit(&r, 0.001, f64::INFINITY, &mut rng)
+                    .map(|h| h.t);
+                assert_eq!(got, expected, "refitted frame {}", frame);
+                let got = fresh.hit(&r, 0.001, f64::INFINITY, &mut rng).map(|h| h.t);
+                assert_eq!(got, expected, "rebuilt frame {}", frame);
+            }
+        }
     }
 }
-unsafe impl Send for BvhNode {}
-unsafe impl Sync for BvhNode {}
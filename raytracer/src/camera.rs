@@ -1,7 +1,8 @@
 use crate::{
-    randoms::{random_double, random_in_unit_disk},
+    randoms::sample_disk,
     ray::Ray,
-    vec3::{mul_num, mul_vec_cross, Point3, Vec3},
+    sampler::Sampler,
+    vec3::{mul_num, mul_vec_cross, mul_vec_dot, Point3, Vec3},
 };
 pub struct NewCamMessage {
     //为了减少变量数目
@@ -82,17 +83,52 @@ impl Camera {
             time1: m._time1,
         }
     }
-    pub fn get_ray(&self, s: f64, t: f64) -> Ray {
-        let rd = random_in_unit_disk() * self.lens_radius;
+    pub fn get_ray(&self, s: f64, t: f64, sampler: &mut dyn Sampler) -> Ray {
+        let rd = sample_disk(sampler.get_2d()) * self.lens_radius;
         let offset = self.u * rd.e.0 + self.v * rd.e.1;
         Ray {
             orig: (self.origin + offset),
             dir: (self.lower_left_corner + mul_num(self.horizonal, s) + mul_num(self.vertical, t)
                 - self.origin
                 - offset),
-            time: random_double(self.time0, self.time1),
+            time: self.sample_time(sampler.get_1d()),
         }
     }
+    //u 在 [0,1)，映射到快门时间内
+    pub fn sample_time(&self, u: f64) -> f64 {
+        self.time0 + (self.time1 - self.time0) * u
+    }
+    //镜头上均匀取一点，针孔相机即为 origin
+    pub fn sample_lens(&self, sampler: &mut dyn Sampler) -> Point3 {
+        let rd = sample_disk(sampler.get_2d()) * self.lens_radius;
+        self.origin + self.u * rd.e.0 + self.v * rd.e.1
+    }
+    //视线方向与镜头到成像面（对焦面）的距离
+    fn forward(&self) -> (Vec3, f64) {
+        let c = self.lower_left_corner + self.horizonal / 2.0 + self.vertical / 2.0 - self.origin;
+        (c.unit_vector(), c.length())
+    }
+    //从镜头上的 lens 出发、经过 p 的光线落在成像面上的 (s,t)（即 get_ray 的参数），
+    //以及光线与视线方向夹角的余弦；p 不在镜头前方时为 None
+    pub fn project(&self, lens: &Point3, p: &Point3) -> Option<(f64, f64, f64)> {
+        let (w, focus_dist) = self.forward();
+        let d = *p - *lens;
+        let cos = mul_vec_dot(d, w) / d.length();
+        if cos.is_nan() || cos <= 0.0 {
+            return None;
+        }
+        let q = *lens + d * (focus_dist / mul_vec_dot(d, w)) - self.lower_left_corner;
+        Some((
+            mul_vec_dot(q, self.horizonal) / self.horizonal.length_square(),
+            mul_vec_dot(q, self.vertical) / self.vertical.length_square(),
+            cos,
+        ))
+    }
+    //s、t 各变化 1 所围的成像面面积，换算到离镜头距离为 1 处
+    pub fn unit_area(&self) -> f64 {
+        let (_, focus_dist) = self.forward();
+        self.horizonal.length() * self.vertical.length() / (focus_dist * focus_dist)
+    }
 }
 impl Default for Camera {
     fn default() -> Self {
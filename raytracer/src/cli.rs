@@ -0,0 +1,190 @@
+use clap::{Parser, Subcommand};
+
+#[derive(Parser)]
+#[clap(name = "raytracer", about = "A toy ray tracer")]
+pub struct Cli {
+    #[clap(subcommand)]
+    pub command: Option<Command>,
+}
+
+#[derive(Subcommand)]
+#[allow(clippy::large_enum_variant)]
+pub enum Command {
+    /// Render a built-in scene or a scene file
+    Render(RenderArgs),
+    /// Darken the edges of an existing image
+    Edgedetect(EdgeDetectArgs),
+    /// List the built-in scenes and the example scene files
+    ListScenes,
+    /// Build the BVH of OBJ models and print its quality
+    Bvh(BvhArgs),
+}
+
+#[derive(Parser)]
+pub struct RenderArgs {
+    /// Name of a built-in scene
+    #[clap(short, long, default_value = "earth")]
+    pub scene: String,
+    /// Path of a .json/.yaml scene file, used instead of --scene
+    #[clap(short = 'f', long)]
+    pub scene_file: Option<String>,
+    /// Samples per pixel; the upper bound when sampling adaptively
+    #[clap(long, default_value_t = 10)]
+    pub spp: u32,
+    /// Sample adaptively: stop a pixel once the relative standard error of its luminance
+    /// falls below this value
+    #[clap(long)]
+    pub error_target: Option<f64>,
+    /// Samples every pixel gets before adaptive sampling may stop it
+    #[clap(long, default_value_t = 16)]
+    pub min_spp: u32,
+    /// Write a heat-map of the samples used per pixel to this image
+    #[clap(long)]
+    pub spp_map: Option<String>,
+    /// Write a heat-map of the BVH traversal cost (nodes visited plus primitives tested) per
+    /// sample of each pixel to this image
+    #[clap(long)]
+    pub cost_map: Option<String>,
+    /// Print the ray and intersection counts of every thread, not only the total rate
+    #[clap(long)]
+    pub stats: bool,
+    /// Samples per pixel added in each progressive pass; a preview is written after every pass
+    #[clap(long, default_value_t = 16)]
+    pub pass_spp: u32,
+    /// Checkpoint file with the accumulated radiance; defaults to the output path plus ".ckpt"
+    #[clap(long)]
+    pub checkpoint: Option<String>,
+    /// Seconds between checkpoints during the render (0 writes one only on Ctrl-C or when --checkpoint is given)
+    #[clap(long, default_value_t = 300)]
+    pub checkpoint_interval: u64,
+    /// Continue from the checkpoint and add samples until --spp is reached
+    #[clap(long)]
+    pub resume: bool,
+    /// Maximum number of bounces of a path
+    #[clap(long, default_value_t = 50)]
+    pub depth: i32,
+    /// Number of bounces before Russian roulette may end a path
+    #[clap(long, default_value_t = 5)]
+    pub rr_depth: i32,
+    /// Light transport algorithm: path (path tracing with next event estimation), bdpt
+    /// (bidirectional path tracing) or photon (path tracing with a caustic photon map)
+    #[clap(long, default_value = "path")]
+    pub integrator: String,
+    /// Trace sampled wavelengths instead of RGB, so dispersive glass splits light into
+    /// colours; only the path integrator supports it
+    #[clap(long)]
+    pub spectral: bool,
+    /// Photons emitted from the lights for each photon map
+    #[clap(long, default_value_t = 200000)]
+    pub photons: u32,
+    /// Gather radius of the photon map; defaults to 0.25% of the scene's bounding box diagonal
+    #[clap(long)]
+    pub photon_radius: Option<f64>,
+    /// Trace a new photon map for every pass and shrink the gather radius, so the caustics
+    /// converge as samples are added
+    #[clap(long)]
+    pub progressive: bool,
+    /// How slowly the progressive gather radius shrinks, between 0 and 1
+    #[clap(long, default_value_t = 0.7)]
+    pub photon_alpha: f64,
+    /// How light and material samples are combined: none (one sample from their
+    /// mixture), balance or power (multiple importance sampling); bdpt treats none as balance
+    #[clap(long, default_value = "power")]
+    pub heuristic: String,
+    /// Share of light sampling, between 0 and 1
+    #[clap(long, default_value_t = 0.5)]
+    pub light_weight: f64,
+    /// Sample generator for pixel, lens, light and material samples: independent,
+    /// stratified, halton or sobol
+    #[clap(long, default_value = "sobol")]
+    pub sampler: String,
+    /// Pixel reconstruction filter each sample is splatted with: box, tent, gaussian, mitchell
+    /// or blackman_harris
+    #[clap(long, default_value = "box")]
+    pub filter: String,
+    /// Filter radius in pixels; defaults to 0.5 for box, 1 for tent, 1.5 for gaussian and 2
+    /// for mitchell and blackman_harris
+    #[clap(long)]
+    pub filter_radius: Option<f64>,
+    /// Seed of all random numbers; the same seed and settings give the same image for any
+    /// thread count
+    #[clap(long, default_value_t = 0)]
+    pub seed: u64,
+    /// Number of render threads
+    #[clap(short = 'j', long, default_value_t = 20)]
+    pub threads: u32,
+    /// Edge length of the square tiles handed out to the threads
+    #[clap(long, default_value_t = 32)]
+    pub tile_size: u32,
+    /// Order in which tiles are rendered: spiral (from the centre) or scanline
+    #[clap(long, default_value = "spiral")]
+    pub tile_order: String,
+    /// Image width; the height follows the aspect ratio of the scene
+    #[clap(long, conflicts_with = "height")]
+    pub width: Option<u32>,
+    /// Image height; the width follows the aspect ratio of the scene
+    #[clap(long)]
+    pub height: Option<u32>,
+    /// First frame of an animation; frames are written as numbered images
+    #[clap(long)]
+    pub frame_start: Option<u32>,
+    /// Last frame of an animation; defaults to the frame range covered by the keyframes of the
+    /// scene file
+    #[clap(long)]
+    pub frame_end: Option<u32>,
+    /// Output image path; .exr, .hdr and .pfm keep the linear radiance, .png, .jpg, .ppm and
+    /// .bmp are gamma-corrected 8-bit images. In an animation a run of '#' is replaced by the
+    /// frame number, otherwise the number is put before the extension
+    #[clap(short, long, default_value = "output/book3/work.jpg")]
+    pub output: String,
+    /// Output format (exr, hdr, pfm, png, jpeg, ppm or bmp); taken from the output extension if
+    /// omitted
+    #[clap(long)]
+    pub format: Option<String>,
+    /// Tone mapping for 8-bit output: linear, reinhard, aces or hable
+    #[clap(long, default_value = "linear")]
+    pub tonemap: String,
+    /// Exposure in EV (stops) applied before tone mapping
+    #[clap(long, default_value_t = 0.0, allow_hyphen_values = true)]
+    pub exposure: f64,
+    /// Luminance that Reinhard maps to white; plain L/(1+L) if omitted
+    #[clap(long)]
+    pub white_point: Option<f64>,
+    /// Extra passes written next to the output, comma separated: shading_normal,
+    /// geometric_normal, albedo, depth, position, uv, object_id, material_id, emitted,
+    /// scattered or all
+    #[clap(long, use_value_delimiter = true)]
+    pub aov: Vec<String>,
+    /// Denoise the result with an edge-avoiding À-trous filter guided by albedo, normal and
+    /// depth; the unfiltered image is written as <name>.noisy.<ext>
+    #[clap(long)]
+    pub denoise: bool,
+    /// Denoiser strength; larger values smooth more across edges
+    #[clap(long, default_value_t = 1.0)]
+    pub denoise_strength: f64,
+    /// Number of À-trous passes; the filter footprint doubles with each pass
+    #[clap(long, default_value_t = 5)]
+    pub denoise_iterations: u32,
+    /// JPEG quality
+    #[clap(long, default_value_t = 100)]
+    pub quality: u8,
+}
+
+#[derive(Parser)]
+pub struct EdgeDetectArgs {
+    /// Source image
+    #[clap(default_value = "output/book3/workfinal.jpg")]
+    pub input: String,
+    /// Output image
+    #[clap(default_value = "output/book3/test_ran_scene_e2.jpg")]
+    pub output: String,
+    /// Number of threads
+    #[clap(short = 'j', long, default_value_t = 20)]
+    pub threads: u32,
+}
+
+#[derive(Parser)]
+pub struct BvhArgs {
+    /// Names of models in raytracer/objects; every model with an .obj file when empty
+    pub models: Vec<String>,
+}
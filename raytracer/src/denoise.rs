@@ -0,0 +1,121 @@
+use crate::framebuffer::HdrImage;
+
+//边缘保持的 À-trous 小波滤波（Dammertz 等，2010），颜色权重按像素方差缩放（同 SVGF）
+//先除以反照率只对光照部分滤波，权重由亮度、法线、深度、反照率的差异共同决定，最后再乘回反照率
+pub struct Denoiser {
+    pub iterations: u32,
+    //越大越平滑，1 为默认
+    pub strength: f32,
+}
+
+//与结果图同尺寸的引导图
+pub struct Guides<'a> {
+    pub albedo: &'a HdrImage,
+    pub normal: &'a HdrImage,
+    pub depth: &'a HdrImage,
+    //每个像素均值亮度的方差
+    pub variance: &'a [f32],
+}
+
+const KERNEL: [f32; 5] = [1.0 / 16.0, 1.0 / 4.0, 3.0 / 8.0, 1.0 / 4.0, 1.0 / 16.0];
+
+fn luminance(c: &[f32; 3]) -> f32 {
+    0.2126 * c[0] + 0.7152 * c[1] + 0.0722 * c[2]
+}
+
+fn dist2(a: &[f32; 3], b: &[f32; 3]) -> f32 {
+    (a[0] - b[0]).powi(2) + (a[1] - b[1]).powi(2) + (a[2] - b[2]).powi(2)
+}
+
+impl Denoiser {
+    pub fn denoise(&self, img: &HdrImage, guides: &Guides) -> HdrImage {
+        let (w, h) = (img.width as i64, img.height as i64);
+        //反照率太暗（光源、背景）时不除
+        let albedo: Vec<[f32; 3]> = guides
+            .albedo
+            .data
+            .iter()
+            .map(|a| a.map(|x| if x > 0.01 { x } else { 1.0 }))
+            .collect();
+        let mut light: Vec<[f32; 3]> = img
+            .data
+            .iter()
+            .zip(albedo.iter())
+            .map(|(c, a)| {
+                let c = c.map(|x| if x.is_finite() { x.max(0.0) } else { 0.0 });
+                [c[0] / a[0], c[1] / a[1], c[2] / a[2]]
+            })
+            .collect();
+        //方差也要除以反照率的平方
+        let mut variance: Vec<f32> = guides
+            .variance
+            .iter()
+            .zip(albedo.iter())
+            .map(|(v, a)| v / luminance(a).powi(2))
+            .collect();
+        let sigma_lum = 4.0 * self.strength.max(1e-3);
+        let sigma_normal = 0.3 * self.strength.max(1e-3);
+        let sigma_depth = 0.05 * self.strength.max(1e-3);
+        let sigma_albedo = 0.1 * self.strength.max(1e-3);
+        for it in 0..self.iterations {
+            let step = 1i64 << it;
+            let lum: Vec<f32> = light.iter().map(luminance).collect();
+            let mut next = vec![[0.0f32; 3]; light.len()];
+            let mut next_var = vec![0.0f32; light.len()];
+            for y in 0..h {
+                for x in 0..w {
+                    let p = (y * w + x) as usize;
+                    let dp = guides.depth.data[p][0];
+                    let lum_scale = sigma_lum * variance[p].max(0.0).sqrt() + 1e-4;
+                    let mut sum = [0.0f32; 3];
+                    let mut var_sum = 0.0f32;
+                    let mut wsum = 0.0f32;
+                    for (j, kj) in KERNEL.iter().enumerate() {
+                        let qy = y + (j as i64 - 2) * step;
+                        if qy < 0 || qy >= h {
+                            continue;
+                        }
+                        for (i, ki) in KERNEL.iter().enumerate() {
+                            let qx = x + (i as i64 - 2) * step;
+                            if qx < 0 || qx >= w {
+                                continue;
+                            }
+                            let q = (qy * w + qx) as usize;
+                            let wl = (-(lum[p] - lum[q]).abs() / lum_scale).exp();
+                            let wn = (-dist2(&guides.normal.data[p], &guides.normal.data[q])
+                                / (sigma_normal * sigma_normal))
+                                .exp();
+                            let dq = guides.depth.data[q][0];
+                            let wd = (-(dp - dq).abs()
+                                / (sigma_depth * dp.max(1e-3) * step as f32))
+                                .exp();
+                            let wa = (-dist2(&guides.albedo.data[p], &guides.albedo.data[q])
+                                / (sigma_albedo * sigma_albedo))
+                                .exp();
+                            let weight = kj * ki * wl * wn * wd * wa;
+                            for c in 0..3 {
+                                sum[c] += weight * light[q][c];
+                            }
+                            var_sum += weight * weight * variance[q];
+                            wsum += weight;
+                        }
+                    }
+                    next[p] = sum.map(|s| s / wsum);
+                    next_var[p] = var_sum / (wsum * wsum);
+                }
+            }
+            light = next;
+            variance = next_var;
+        }
+        let data = light
+            .iter()
+            .zip(albedo.iter())
+            .map(|(l, a)| [l[0] * a[0], l[1] * a[1], l[2] * a[2]])
+            .collect();
+        HdrImage {
+            width: img.width,
+            height: img.height,
+            data,
+        }
+    }
+}
@@ -15,8 +15,8 @@ fn pixel_gray_value(rgb: &Rgb<u8>) -> i32 {
     (gray_value(rgb.0[0], rgb.0[1], rgb.0[2])) as i32
 }
 pub fn edgedetect(source: &str, output: &str, thread_num: u32) {
-    let source = format!("output/{}", source); //依需求调整
-    let source_path = Path::new(&source);
+    let thread_num = thread_num.max(1);
+    let source_path = Path::new(source);
     let img_: DynamicImage = image::open(source_path).expect("failed");
     let rgb_img: RgbImage = match img_ {
         DynamicImage::ImageRgb8(rgb_img) => rgb_img,
@@ -26,10 +26,10 @@ pub fn edgedetect(source: &str, output: &str, thread_num: u32) {
     let height = rgb_img.height();
     let source_im = Arc::new(rgb_img);
     let quality = 100;
-    let output = format!("output/{}", output);
-    let output_path = Path::new(&output);
-    let prefix = output_path.parent().unwrap();
-    std::fs::create_dir_all(prefix).expect("Cannot create all the parents");
+    let output_path = Path::new(output);
+    if let Some(prefix) = output_path.parent() {
+        std::fs::create_dir_all(prefix).expect("Cannot create all the parents");
+    }
     let img2: RgbImage = ImageBuffer::new(width, height);
     let im2 = Arc::new(Mutex::new(img2));
     let main_progress = Arc::new(Mutex::new(MultiProgress::new()));
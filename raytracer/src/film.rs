@@ -0,0 +1,241 @@
+use std::f64::consts::PI;
+
+use crate::vec3::Color;
+
+pub const FILTER_NAMES: [&str; 5] = ["box", "tent", "gaussian", "mitchell", "blackman_harris"];
+
+#[derive(Clone, Copy, PartialEq)]
+pub enum FilterKind {
+    Box,
+    Tent,
+    Gaussian,
+    //B = C = 1/3
+    Mitchell,
+    BlackmanHarris,
+}
+
+//像素重建滤波，可分离：权重为 f(dx)·f(dy)，dx,dy 为采样到像素中心的距离（单位为像素）
+#[derive(Clone, Copy, PartialEq)]
+pub struct Filter {
+    pub kind: FilterKind,
+    pub radius: f64,
+}
+
+impl Filter {
+    //radius 为 None 时用各滤波的常用半径
+    pub fn from_name(name: &str, radius: Option<f64>) -> Option<Self> {
+        let (kind, default_radius) = match name {
+            "box" => (FilterKind::Box, 0.5),
+            "tent" => (FilterKind::Tent, 1.0),
+            "gaussian" => (FilterKind::Gaussian, 1.5),
+            "mitchell" => (FilterKind::Mitchell, 2.0),
+            "blackman_harris" => (FilterKind::BlackmanHarris, 2.0),
+            _ => return None,
+        };
+        let radius = radius.unwrap_or(default_radius);
+        if radius.is_nan() || radius <= 0.0 {
+            return None;
+        }
+        Some(Self { kind, radius })
+    }
+    pub fn name(&self) -> &'static str {
+        match self.kind {
+            FilterKind::Box => "box",
+            FilterKind::Tent => "tent",
+            FilterKind::Gaussian => "gaussian",
+            FilterKind::Mitchell => "mitchell",
+            FilterKind::BlackmanHarris => "blackman_harris",
+        }
+    }
+    //采样最远能影响到几个像素之外
+    pub fn reach(&self) -> u32 {
+        self.radius.ceil() as u32
+    }
+    fn eval_1d(&self, x: f64) -> f64 {
+        let r = self.radius;
+        match self.kind {
+            //左闭右开，半径 0.5 时每个采样恰好只落进一个像素
+            FilterKind::Box => {
+                if x >= -r && x < r {
+                    1.0
+                } else {
+                    0.0
+                }
+            }
+            FilterKind::Tent => (1.0 - x.abs() / r).max(0.0),
+            //σ 取半径的 1/3，并减去边界处的值使其在半径处连续降到 0
+            FilterKind::Gaussian => {
+                let sigma = r / 3.0;
+                let g = |x: f64| (-x * x / (2.0 * sigma * sigma)).exp();
+                (g(x) - g(r)).max(0.0)
+            }
+            FilterKind::Mitchell => {
+                let (b, c) = (1.0 / 3.0, 1.0 / 3.0);
+                let x = (2.0 * x / r).abs();
+                if x > 2.0 {
+                    0.0
+                } else if x > 1.0 {
+                    ((-b - 6.0 * c) * x * x * x
+                        + (6.0 * b + 30.0 * c) * x * x
+                        + (-12.0 * b - 48.0 * c) * x
+                        + (8.0 * b + 24.0 * c))
+                        / 6.0
+                } else {
+                    ((12.0 - 9.0 * b - 6.0 * c) * x * x * x
+                        + (-18.0 + 12.0 * b + 6.0 * c) * x * x
+                        + (6.0 - 2.0 * b))
+                        / 6.0
+                }
+            }
+            //四项 Blackman-Harris 窗，覆盖 [-r, r]
+            FilterKind::BlackmanHarris => {
+                if x.abs() >= r {
+                    return 0.0;
+                }
+                let t = 2.0 * PI * (x + r) / (2.0 * r);
+                0.35875 - 0.48829 * t.cos() + 0.14128 * (2.0 * t).cos() - 0.01168 * (3.0 * t).cos()
+            }
+        }
+    }
+    pub fn eval(&self, dx: f64, dy: f64) -> f64 {
+        self.eval_1d(dx) * self.eval_1d(dy)
+    }
+}
+
+//图像上一块矩形区域里按滤波权重累加的辐亮度与权重之和，x0,y0 为左上角（图像坐标，y 向下）；
+//渲染时每个块的区域向外扩 reach 个像素，合并时再加到整幅图上
+pub struct Film {
+    pub filter: Filter,
+    pub x0: u32,
+    pub y0: u32,
+    pub width: u32,
+    pub height: u32,
+    pub sum: Vec<Color>,
+    pub weight: Vec<f64>,
+}
+
+impl Film {
+    pub fn new(filter: Filter, x0: u32, y0: u32, width: u32, height: u32) -> Self {
+        let n = (width * height) as usize;
+        Self {
+            filter,
+            x0,
+            y0,
+            width,
+            height,
+            sum: vec![Color::new(); n],
+            weight: vec![0.0; n],
+        }
+    }
+    //在 image_width x image_height 的图像里，把 (x0,y0) 起 width x height 的区域向外扩 reach 个像素
+    pub fn padded(
+        filter: Filter,
+        x0: u32,
+        y0: u32,
+        width: u32,
+        height: u32,
+        image_width: u32,
+        image_height: u32,
+    ) -> Self {
+        let reach = filter.reach();
+        let (x1, y1) = (
+            (x0 + width + reach).min(image_width),
+            (y0 + height + reach).min(image_height),
+        );
+        let (x0, y0) = (x0.saturating_sub(reach), y0.saturating_sub(reach));
+        Self::new(filter, x0, y0, x1 - x0, y1 - y0)
+    }
+    //x,y 为采样在图像上的连续坐标，像素 (i,j) 的中心在 (i+0.5, j+0.5)
+    pub fn splat(&mut self, x: f64, y: f64, c: Color) {
+        if !(c.e.0.is_finite() && c.e.1.is_finite() && c.e.2.is_finite()) {
+            return;
+        }
+        let r = self.filter.radius;
+        let lo = |p: f64, start: u32| ((p - 0.5 - r).floor().max(start as f64)) as u32;
+        let hi = |p: f64, start: u32, len: u32| {
+            ((p - 0.5 + r).ceil() as i64).clamp(start as i64 - 1, (start + len) as i64 - 1)
+        };
+        let (i0, i1) = (lo(x, self.x0), hi(x, self.x0, self.width));
+        let (j0, j1) = (lo(y, self.y0), hi(y, self.y0, self.height));
+        for j in j0 as i64..=j1 {
+            for i in i0 as i64..=i1 {
+                let w = self.filter.eval(x - (i as f64 + 0.5), y - (j as f64 + 0.5));
+                if w == 0.0 {
+                    continue;
+                }
+                let k = ((j as u32 - self.y0) * self.width + i as u32 - self.x0) as usize;
+                self.sum[k] += c * w;
+                self.weight[k] += w;
+            }
+        }
+    }
+    //把另一块加进来，other 须在本区域之内
+    pub fn add(&mut self, other: &Film) {
+        for dj in 0..other.height {
+            for di in 0..other.width {
+                let src = (dj * other.width + di) as usize;
+                let dst =
+                    ((other.y0 - self.y0 + dj) * self.width + other.x0 - self.x0 + di) as usize;
+                self.sum[dst] += other.sum[src];
+                self.weight[dst] += other.weight[src];
+            }
+        }
+    }
+    //加权平均；权重和接近 0 时（如 Mitchell 负瓣抵消）取 None
+    pub fn pixel(&self, k: usize) -> Option<Color> {
+        let w = self.weight[k];
+        if w.abs() < 1e-8 {
+            None
+        } else {
+            Some(self.sum[k] / w)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn box_and_tent_weights_sum_to_one() {
+        //半径 0.5 的盒式与半径 1 的帐篷滤波，一个采样分到各像素的权重之和恰为 1
+        for &(name, r) in [("box", 0.5), ("tent", 1.0)].iter() {
+            let filter = Filter::from_name(name, Some(r)).unwrap();
+            for s in 0..50 {
+                let (x, y) = (5.0 + s as f64 * 0.0371, 5.0 + s as f64 * 0.0193);
+                let mut film = Film::new(filter, 0, 0, 10, 10);
+                film.splat(x, y, Color { e: (1.0, 1.0, 1.0) });
+                let sum: f64 = film.weight.iter().sum();
+                assert!(
+                    (sum - 1.0).abs() < 1e-12,
+                    "{} at ({}, {}): {}",
+                    name,
+                    x,
+                    y,
+                    sum
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn normalized_weights_keep_a_constant_image() {
+        //归一化之后每个像素的权重之和为 1，常数图像经过任何滤波都不变，边缘像素也一样
+        let c = Color {
+            e: (0.25, 0.5, 2.0),
+        };
+        for name in FILTER_NAMES.iter() {
+            let filter = Filter::from_name(name, None).unwrap();
+            let mut film = Film::new(filter, 0, 0, 7, 5);
+            for j in 0..20 {
+                for i in 0..28 {
+                    film.splat((i as f64 + 0.5) / 4.0, (j as f64 + 0.5) / 4.0, c);
+                }
+            }
+            for k in 0..film.weight.len() {
+                let p = film.pixel(k).unwrap();
+                assert!((p - c).length() < 1e-9, "{} pixel {}", name, k);
+            }
+        }
+    }
+}
@@ -0,0 +1,345 @@
+use std::{
+    fs::File,
+    io::{BufReader, BufWriter, Read, Write},
+};
+
+use image::{ImageBuffer, RgbImage};
+
+use crate::{
+    aov::{Aov, AovBuffer},
+    film::{Film, Filter},
+    randoms::clamp,
+    tile::TileBuffer,
+    vec3::Color,
+};
+
+const CHECKPOINT_MAGIC: &[u8; 4] = b"RTCK";
+const CHECKPOINT_VERSION: u32 = 4;
+
+pub fn luminance(c: &Color) -> f64 {
+    0.2126 * c.e.0 + 0.7152 * c.e.1 + 0.0722 * c.e.2
+}
+
+//t 在 [0,1]，由蓝经青、绿、黄到红
+pub fn heat_color(t: f64) -> image::Rgb<u8> {
+    let t = clamp(t, 0.0, 1.0) * 4.0;
+    let (r, g, b) = if t < 1.0 {
+        (0.0, t, 1.0)
+    } else if t < 2.0 {
+        (0.0, 1.0, 2.0 - t)
+    } else if t < 3.0 {
+        (t - 2.0, 1.0, 0.0)
+    } else {
+        (1.0, 4.0 - t, 0.0)
+    };
+    image::Rgb([(255.0 * r) as u8, (255.0 * g) as u8, (255.0 * b) as u8])
+}
+
+//线性 RGB 图像，按行存储，第 0 行在最上面
+pub struct HdrImage {
+    pub width: u32,
+    pub height: u32,
+    pub data: Vec<[f32; 3]>,
+}
+
+//按像素累计的辐亮度之和、亮度平方和与采样数，各 pass 的结果都加到这里；
+//方差与自适应采样只看落在像素本身的采样，输出的图像用 film 里滤波后的结果；
+//splat 为 bdpt 从光源子路径直接连到相机的贡献之和，按整幅图的平均采样数归一化后加到结果上
+pub struct FrameBuffer {
+    pub width: u32,
+    pub height: u32,
+    pub sum: Vec<Color>,
+    pub sum_sq: Vec<f64>,
+    pub samples: Vec<u32>,
+    pub film: Film,
+    pub splat: Vec<Color>,
+    //AOV 不存进 checkpoint，只包含本次运行的采样
+    pub aov: AovBuffer,
+    //同样只含本次运行：遍历代价之和与对应的采样数
+    pub cost: Vec<u64>,
+    pub cost_samples: Vec<u32>,
+}
+
+impl FrameBuffer {
+    pub fn new(width: u32, height: u32, filter: Filter, aovs: &[Aov]) -> Self {
+        let n = (width * height) as usize;
+        Self {
+            width,
+            height,
+            sum: vec![Color::new(); n],
+            sum_sq: vec![0.0; n],
+            samples: vec![0; n],
+            film: Film::new(filter, 0, 0, width, height),
+            splat: vec![Color::new(); n],
+            aov: AovBuffer::new(width, height, aovs),
+            cost: vec![0; n],
+            cost_samples: vec![0; n],
+        }
+    }
+    pub fn add_tile(&mut self, buffer: &TileBuffer) {
+        let t = buffer.tile;
+        self.film.add(&buffer.film);
+        for sp in &buffer.splats {
+            let c = sp.c;
+            if c.e.0.is_finite() && c.e.1.is_finite() && c.e.2.is_finite() {
+                self.splat[(sp.j * self.width + sp.i) as usize] += c;
+            }
+        }
+        self.aov.add_region(&buffer.aov, t.x0, t.y0);
+        for dj in 0..t.height {
+            for di in 0..t.width {
+                let src = (dj * t.width + di) as usize;
+                let dst = ((t.y0 + dj) * self.width + t.x0 + di) as usize;
+                self.sum[dst] += buffer.sum[src];
+                self.sum_sq[dst] += buffer.sum_sq[src];
+                self.samples[dst] += buffer.samples[src];
+                self.cost[dst] += buffer.cost[src];
+                self.cost_samples[dst] += buffer.samples[src];
+            }
+        }
+    }
+    pub fn min_samples(&self) -> u32 {
+        self.samples.iter().copied().min().unwrap_or(0)
+    }
+    //像素均值亮度的相对标准误差，采样数不足两个时视为无穷大
+    pub fn relative_error(&self, k: usize) -> f64 {
+        let n = self.samples[k] as f64;
+        if n < 2.0 {
+            return f64::INFINITY;
+        }
+        let mean = luminance(&self.sum[k]) / n;
+        let var = ((self.sum_sq[k] / n - mean * mean) * n / (n - 1.0)).max(0.0);
+        (var / n).sqrt() / mean.max(1e-3)
+    }
+    //每个像素均值亮度的方差，采样数不足两个时取该像素亮度的平方
+    pub fn variance(&self) -> Vec<f32> {
+        (0..self.samples.len())
+            .map(|k| {
+                let n = self.samples[k] as f64;
+                let mean = luminance(&self.sum[k]) / n.max(1.0);
+                if n < 2.0 {
+                    return (mean * mean) as f32;
+                }
+                let var = ((self.sum_sq[k] / n - mean * mean) * n / (n - 1.0)).max(0.0);
+                (var / n) as f32
+            })
+            .collect()
+    }
+    //本次 pass 每个像素要加的采样数：先补到 min_spp，之后只给误差仍高于 target 的像素加，不超过 max_spp；
+    //target 为 None 时每个像素都采到 max_spp
+    pub fn pass_samples(
+        &self,
+        pass_spp: u32,
+        min_spp: u32,
+        max_spp: u32,
+        target: Option<f64>,
+    ) -> Vec<u32> {
+        (0..self.samples.len())
+            .map(|k| {
+                let n = self.samples[k];
+                let wanted = match target {
+                    Some(t) if n >= min_spp && self.relative_error(k) <= t => 0,
+                    _ => max_spp.saturating_sub(n),
+                };
+                wanted.min(pass_spp)
+            })
+            .collect()
+    }
+    //每个像素滤波后的辐亮度，线性 f32；权重和为 0 时退回像素内采样的平均
+    pub fn resolve(&self) -> HdrImage {
+        let total: u64 = self.samples.iter().map(|&n| n as u64).sum();
+        let splat_scale = self.samples.len() as f64 / total.max(1) as f64;
+        let data = (0..self.sum.len())
+            .map(|k| {
+                let c = self
+                    .film
+                    .pixel(k)
+                    .unwrap_or_else(|| self.sum[k] / self.samples[k].max(1) as f64)
+                    + self.splat[k] * splat_scale;
+                [c.e.0 as f32, c.e.1 as f32, c.e.2 as f32]
+            })
+            .collect();
+        HdrImage {
+            width: self.width,
+            height: self.height,
+            data,
+        }
+    }
+    //每个像素实际用的采样数，max_spp 对应红色
+    pub fn spp_image(&self, max_spp: u32) -> RgbImage {
+        let mut img: RgbImage = ImageBuffer::new(self.width, self.height);
+        for (k, n) in self.samples.iter().enumerate() {
+            let k = k as u32;
+            let t = *n as f64 / max_spp.max(1) as f64;
+            img.put_pixel(k % self.width, k / self.width, heat_color(t));
+        }
+        img
+    }
+    //每个采样的平均遍历代价，按最大的像素归一化；同时返回这个最大值
+    pub fn cost_image(&self) -> (RgbImage, f64) {
+        let mean: Vec<f64> = self
+            .cost
+            .iter()
+            .zip(&self.cost_samples)
+            .map(|(&c, &n)| if n == 0 { 0.0 } else { c as f64 / n as f64 })
+            .collect();
+        let max = mean.iter().copied().fold(0.0, f64::max);
+        let mut img: RgbImage = ImageBuffer::new(self.width, self.height);
+        for (k, m) in mean.iter().enumerate() {
+            let k = k as u32;
+            let t = if max > 0.0 { m / max } else { 0.0 };
+            img.put_pixel(k % self.width, k / self.width, heat_color(t));
+        }
+        (img, max)
+    }
+    //checkpoint 格式：magic、版本、宽、高、场景名、滤波名、滤波半径（f64），
+    //然后逐像素十一个 f64（颜色之和与亮度平方和，滤波后的权重与颜色之和，splat 之和）与一个 u32，均为小端
+    pub fn save_checkpoint(&self, path: &str, scene: &str) -> Result<(), String> {
+        let err = |e: std::io::Error| format!("cannot write checkpoint {}: {}", path, e);
+        let mut w = BufWriter::new(File::create(path).map_err(err)?);
+        w.write_all(CHECKPOINT_MAGIC).map_err(err)?;
+        for x in [
+            CHECKPOINT_VERSION,
+            self.width,
+            self.height,
+            scene.len() as u32,
+        ] {
+            w.write_all(&x.to_le_bytes()).map_err(err)?;
+        }
+        w.write_all(scene.as_bytes()).map_err(err)?;
+        let filter = self.film.filter;
+        w.write_all(&(filter.name().len() as u32).to_le_bytes())
+            .map_err(err)?;
+        w.write_all(filter.name().as_bytes()).map_err(err)?;
+        w.write_all(&filter.radius.to_le_bytes()).map_err(err)?;
+        for k in 0..self.sum.len() {
+            let (c, f, sp) = (self.sum[k], self.film.sum[k], self.splat[k]);
+            for x in [
+                c.e.0,
+                c.e.1,
+                c.e.2,
+                self.sum_sq[k],
+                self.film.weight[k],
+                f.e.0,
+                f.e.1,
+                f.e.2,
+                sp.e.0,
+                sp.e.1,
+                sp.e.2,
+            ] {
+                w.write_all(&x.to_le_bytes()).map_err(err)?;
+            }
+            w.write_all(&self.samples[k].to_le_bytes()).map_err(err)?;
+        }
+        w.flush().map_err(err)
+    }
+    //返回帧缓冲与写入时的场景名，滤波按 checkpoint 里记录的来
+    pub fn load_checkpoint(path: &str) -> Result<(Self, String), String> {
+        let err = |e: std::io::Error| format!("cannot read checkpoint {}: {}", path, e);
+        let mut r = BufReader::new(File::open(path).map_err(err)?);
+        let mut magic = [0u8; 4];
+        r.read_exact(&mut magic).map_err(err)?;
+        if &magic != CHECKPOINT_MAGIC {
+            return Err(format!("{} is not a checkpoint file", path));
+        }
+        let mut u32_buf = [0u8; 4];
+        let mut read_u32 = |r: &mut BufReader<File>| -> Result<u32, String> {
+            r.read_exact(&mut u32_buf).map_err(err)?;
+            Ok(u32::from_le_bytes(u32_buf))
+        };
+        let version = read_u32(&mut r)?;
+        if version != CHECKPOINT_VERSION {
+            return Err(format!(
+                "checkpoint {} has version {}, expected {}",
+                path, version, CHECKPOINT_VERSION
+            ));
+        }
+        let width = read_u32(&mut r)?;
+        let height = read_u32(&mut r)?;
+        let name_len = read_u32(&mut r)?;
+        let mut name = vec![0u8; name_len as usize];
+        r.read_exact(&mut name).map_err(err)?;
+        let scene = String::from_utf8_lossy(&name).into_owned();
+        let filter_len = read_u32(&mut r)?;
+        let mut filter_name = vec![0u8; filter_len as usize];
+        r.read_exact(&mut filter_name).map_err(err)?;
+        let mut f64_buf = [0u8; 8];
+        r.read_exact(&mut f64_buf).map_err(err)?;
+        let filter = Filter::from_name(
+            &String::from_utf8_lossy(&filter_name),
+            Some(f64::from_le_bytes(f64_buf)),
+        )
+        .ok_or(format!("checkpoint {} has an unknown filter", path))?;
+        let mut fb = Self::new(width, height, filter, &[]);
+        for k in 0..(width * height) as usize {
+            let mut e = [0.0; 11];
+            for x in e.iter_mut() {
+                r.read_exact(&mut f64_buf).map_err(err)?;
+                *x = f64::from_le_bytes(f64_buf);
+            }
+            fb.sum[k] = Color {
+                e: (e[0], e[1], e[2]),
+            };
+            fb.sum_sq[k] = e[3];
+            fb.film.weight[k] = e[4];
+            fb.film.sum[k] = Color {
+                e: (e[5], e[6], e[7]),
+            };
+            fb.splat[k] = Color {
+                e: (e[8], e[9], e[10]),
+            };
+            fb.samples[k] = read_u32(&mut r)?;
+        }
+        Ok((fb, scene))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn checkpoint_round_trip() {
+        let filter = Filter::from_name("mitchell", Some(1.7)).unwrap();
+        let mut fb = FrameBuffer::new(3, 2, filter, &[]);
+        for k in 0..6 {
+            let x = k as f64;
+            fb.film.sum[k] = Color {
+                e: (x, 2.0 * x, -0.5),
+            };
+            fb.film.weight[k] = 0.75 * x - 1.0;
+            fb.sum[k] = Color {
+                e: (x * 0.5, -x, 1.0 / (x + 3.0)),
+            };
+            fb.sum_sq[k] = x * x + 0.25;
+            fb.samples[k] = 7 * k as u32 + 1;
+        }
+        let path = std::env::temp_dir().join(format!("rt-ckpt-{}.ckpt", std::process::id()));
+        let path = path.to_str().unwrap();
+        fb.save_checkpoint(path, "cornell_box").unwrap();
+        let (back, scene) = FrameBuffer::load_checkpoint(path).unwrap();
+        std::fs::remove_file(path).unwrap();
+        assert_eq!(scene, "cornell_box");
+        assert_eq!((back.width, back.height), (3, 2));
+        assert_eq!(back.samples, fb.samples);
+        assert_eq!(back.sum_sq, fb.sum_sq);
+        assert_eq!(back.film.weight, fb.film.weight);
+        assert!(back.film.filter == filter);
+        for (a, b) in back.sum.iter().zip(fb.sum.iter()) {
+            assert_eq!(a.e, b.e);
+        }
+        for (a, b) in back.film.sum.iter().zip(fb.film.sum.iter()) {
+            assert_eq!(a.e, b.e);
+        }
+    }
+
+    #[test]
+    fn load_rejects_other_files() {
+        let path = std::env::temp_dir().join(format!("rt-not-ckpt-{}.ckpt", std::process::id()));
+        let path = path.to_str().unwrap();
+        std::fs::write(path, b"P6\n1 1\n255\n\0\0\0").unwrap();
+        let r = FrameBuffer::load_checkpoint(path);
+        std::fs::remove_file(path).unwrap();
+        assert!(r.is_err());
+    }
+}
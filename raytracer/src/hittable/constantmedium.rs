@@ -1,7 +1,7 @@
 use crate::aabb::AABB;
 use crate::material::isotropic::Isotropic;
 use crate::material::Material;
-use crate::random_double;
+use crate::randoms::Rng;
 use crate::ray::Ray;
 use crate::texture::Texture;
 use crate::vec3::Vec3;
@@ -24,12 +24,12 @@ impl<H: Hittable, T: Clone + Texture> ConstantMedium<H, Isotropic<T>> {
     }
 }
 impl<H: Hittable, T: 'static + Clone + Texture> Hittable for ConstantMedium<H, Isotropic<T>> {
-    fn hit(&self, r: &Ray, t_min: f64, t_max: f64) -> Option<HitRecord> {
-        let k1 = self.boundary.hit(r, -INFINITY, INFINITY);
+    fn hit(&self, r: &Ray, t_min: f64, t_max: f64, rng: &mut Rng) -> Option<HitRecord> {
+        let k1 = self.boundary.hit(r, -INFINITY, INFINITY, rng);
 
         k1.as_ref()?;
         let mut rec1 = k1.unwrap();
-        let k2 = self.boundary.hit(r, rec1.t + 0.0001, INFINITY);
+        let k2 = self.boundary.hit(r, rec1.t + 0.0001, INFINITY, rng);
         k2.as_ref()?;
         let mut rec2 = k2.unwrap();
         if rec1.t < t_min {
@@ -46,7 +46,7 @@ impl<H: Hittable, T: 'static + Clone + Texture> Hittable for ConstantMedium<H, I
         }
         let ray_length = r.dir.length();
         let distance_inside_boundary = (rec2.t - rec1.t) * ray_length;
-        let hit_distance = self.neg_inv_density * random_double(0.0, 1.0).log(2.0);
+        let hit_distance = self.neg_inv_density * rng.random_double(0.0, 1.0).log(2.0);
         if hit_distance > distance_inside_boundary {
             return None;
         }
@@ -1,5 +1,7 @@
 use crate::aabb::AABB;
+use crate::randoms::Rng;
 use crate::ray::Ray;
+use crate::sampler::Sampler;
 use crate::vec3::Point3;
 use crate::vec3::Vec3;
 use crate::HitRecord;
@@ -8,8 +10,8 @@ pub struct FlipFace<H: Hittable> {
     pub ptr: H,
 }
 impl<H: Hittable> Hittable for FlipFace<H> {
-    fn hit(&self, r: &Ray, t_min: f64, t_max: f64) -> Option<HitRecord> {
-        let k = self.ptr.hit(r, t_min, t_max);
+    fn hit(&self, r: &Ray, t_min: f64, t_max: f64, rng: &mut Rng) -> Option<HitRecord> {
+        let k = self.ptr.hit(r, t_min, t_max, rng);
         k.as_ref()?;
         let mut rec = k.unwrap();
         rec.front_face = !rec.front_face;
@@ -21,7 +23,13 @@ impl<H: Hittable> Hittable for FlipFace<H> {
     fn pdf_value(&self, o: &Point3, v: &Vec3) -> f64 {
         self.ptr.pdf_value(o, v)
     }
-    fn random(&self, o: &Vec3) -> Vec3 {
-        self.ptr.random(o)
+    fn random(&self, o: &Vec3, sampler: &mut dyn Sampler) -> Vec3 {
+        self.ptr.random(o, sampler)
+    }
+    fn area(&self) -> f64 {
+        self.ptr.area()
+    }
+    fn sample_surface(&self, sampler: &mut dyn Sampler) -> Option<(Point3, Vec3)> {
+        self.ptr.sample_surface(sampler)
     }
 }
@@ -0,0 +1,75 @@
+use crate::aabb::AABB;
+use crate::material::isotropic::Isotropic;
+use crate::material::Material;
+use crate::randoms::Rng;
+use crate::ray::Ray;
+use crate::texture::density::DensityField;
+use crate::texture::Texture;
+use crate::vec3::Vec3;
+use crate::HitRecord;
+use crate::Hittable;
+use std::f64::INFINITY;
+//密度随位置变化的介质：在 boundary 内按密度场的上界（majorant）取候选碰撞点，
+//再以 密度/上界 的概率接受为真实碰撞，否则为虚碰撞继续前进（delta tracking）
+#[derive(Clone)]
+pub struct HeterogeneousMedium<H: Hittable, D: DensityField, M: Material> {
+    pub boundary: H,
+    pub density: D,
+    pub phase_function: M,
+}
+impl<H: Hittable, D: DensityField, T: Clone + Texture> HeterogeneousMedium<H, D, Isotropic<T>> {
+    pub fn new(b: H, density: D, a: T) -> Self {
+        Self {
+            boundary: b,
+            density,
+            phase_function: Isotropic { albedo: a },
+        }
+    }
+}
+impl<H: Hittable, D: DensityField, T: 'static + Clone + Texture> Hittable
+    for HeterogeneousMedium<H, D, Isotropic<T>>
+{
+    fn hit(&self, r: &Ray, t_min: f64, t_max: f64, rng: &mut Rng) -> Option<HitRecord> {
+        let majorant = self.density.max_density();
+        if majorant <= 0.0 {
+            return None;
+        }
+        let mut rec1 = self.boundary.hit(r, -INFINITY, INFINITY, rng)?;
+        let mut rec2 = self.boundary.hit(r, rec1.t + 0.0001, INFINITY, rng)?;
+        if rec1.t < t_min {
+            rec1.t = t_min;
+        }
+        if rec2.t > t_max {
+            rec2.t = t_max;
+        }
+        if rec1.t >= rec2.t {
+            return None;
+        }
+        if rec1.t < 0.0 {
+            rec1.t = 0.0;
+        }
+        let ray_length = r.dir.length();
+        let distance_inside_boundary = (rec2.t - rec1.t) * ray_length;
+        let mut distance = 0.0;
+        loop {
+            distance -= (1.0 - rng.random_double(0.0, 1.0)).ln() / majorant;
+            if distance >= distance_inside_boundary {
+                return None;
+            }
+            let t = rec1.t + distance / ray_length;
+            let p = r.at(t);
+            if rng.random_double(0.0, 1.0) * majorant < self.density.density(&p) {
+                let mut rec = HitRecord::new();
+                rec.t = t;
+                rec.p = p;
+                rec.normal = Vec3 { e: (1.0, 0.0, 0.0) };
+                rec.front_face = true;
+                rec.mat_ptr = &self.phase_function;
+                return Some(rec);
+            }
+        }
+    }
+    fn bounding_box(&self, time0: f64, time1: f64, output_box: &mut AABB) -> bool {
+        self.boundary.bounding_box(time0, time1, output_box)
+    }
+}
@@ -1,7 +1,9 @@
 pub mod constantmedium;
 pub mod flipface;
+pub mod heterogeneousmedium;
 pub mod movingsphere;
 pub mod mybox;
+pub mod objectid;
 pub mod rect;
 pub mod rotate;
 pub mod sphere;
@@ -11,25 +13,77 @@ use crate::aabb::surrounding_box;
 use crate::aabb::AABB;
 use crate::material::dielectric::Dielectric;
 use crate::material::Material;
-use crate::randoms::random_int;
+use crate::randoms::Rng;
 
 use crate::ray::Ray;
+use crate::sampler::Sampler;
 use crate::vec3::mul_vec_dot;
 use crate::vec3::Point3;
 use crate::vec3::Vec3;
-use std::vec::Vec;
+use std::{sync::Arc, vec::Vec};
 
-static NULL_MATERIAL: Dielectric = Dielectric { ir: 0.0 };
+static NULL_MATERIAL: Dielectric = Dielectric::new(0.0);
 
 pub trait Hittable {
-    fn hit(&self, r: &Ray, t_min: f64, t_max: f64) -> Option<HitRecord>;
+    fn hit(&self, r: &Ray, t_min: f64, t_max: f64, rng: &mut Rng) -> Option<HitRecord>;
     fn bounding_box(&self, time0: f64, time1: f64, output_box: &mut AABB) -> bool;
     fn pdf_value(&self, _o: &Point3, _v: &Vec3) -> f64 {
         0.0
     }
-    fn random(&self, _o: &Vec3) -> Vec3 {
+    fn random(&self, _o: &Vec3, _sampler: &mut dyn Sampler) -> Vec3 {
         Vec3 { e: (1.0, 0.0, 0.0) }
     }
+    //表面积，不支持在表面上取点的物体为 0
+    fn area(&self) -> f64 {
+        0.0
+    }
+    //在表面上按面积均匀取一点，返回该点与法线（朝向不定）；BDPT 从光源出发时用
+    fn sample_surface(&self, _sampler: &mut dyn Sampler) -> Option<(Point3, Vec3)> {
+        None
+    }
+}
+
+impl<H: Hittable + ?Sized> Hittable for Box<H> {
+    fn hit(&self, r: &Ray, t_min: f64, t_max: f64, rng: &mut Rng) -> Option<HitRecord> {
+        (**self).hit(r, t_min, t_max, rng)
+    }
+    fn bounding_box(&self, time0: f64, time1: f64, output_box: &mut AABB) -> bool {
+        (**self).bounding_box(time0, time1, output_box)
+    }
+    fn pdf_value(&self, o: &Point3, v: &Vec3) -> f64 {
+        (**self).pdf_value(o, v)
+    }
+    fn random(&self, o: &Vec3, sampler: &mut dyn Sampler) -> Vec3 {
+        (**self).random(o, sampler)
+    }
+    fn area(&self) -> f64 {
+        (**self).area()
+    }
+    fn sample_surface(&self, sampler: &mut dyn Sampler) -> Option<(Point3, Vec3)> {
+        (**self).sample_surface(sampler)
+    }
+}
+
+//动画里各帧共用的物体
+impl<H: Hittable + ?Sized> Hittable for Arc<H> {
+    fn hit(&self, r: &Ray, t_min: f64, t_max: f64, rng: &mut Rng) -> Option<HitRecord> {
+        (**self).hit(r, t_min, t_max, rng)
+    }
+    fn bounding_box(&self, time0: f64, time1: f64, output_box: &mut AABB) -> bool {
+        (**self).bounding_box(time0, time1, output_box)
+    }
+    fn pdf_value(&self, o: &Point3, v: &Vec3) -> f64 {
+        (**self).pdf_value(o, v)
+    }
+    fn random(&self, o: &Vec3, sampler: &mut dyn Sampler) -> Vec3 {
+        (**self).random(o, sampler)
+    }
+    fn area(&self) -> f64 {
+        (**self).area()
+    }
+    fn sample_surface(&self, sampler: &mut dyn Sampler) -> Option<(Point3, Vec3)> {
+        (**self).sample_surface(sampler)
+    }
 }
 
 pub struct HitRecord<'a> {
@@ -40,6 +94,8 @@ pub struct HitRecord<'a> {
     pub mat_ptr: &'a dyn Material,
     pub u: f64,
     pub v: f64,
+    //AOV 用：顶层物体的编号，0 表示未编号
+    pub object_id: u32,
 }
 impl<'a> HitRecord<'a> {
     pub fn set_face_normal(&mut self, r: &Ray, outward_normal: Vec3) {
@@ -59,6 +115,7 @@ impl<'a> HitRecord<'a> {
             mat_ptr: &NULL_MATERIAL,
             u: 0.0,
             v: 0.0,
+            object_id: 0,
         }
     }
 }
@@ -93,12 +150,12 @@ impl Default for HittableList {
     }
 }
 impl Hittable for HittableList {
-    fn hit(&self, r: &Ray, t_min: f64, t_max: f64) -> Option<HitRecord> {
+    fn hit(&self, r: &Ray, t_min: f64, t_max: f64, rng: &mut Rng) -> Option<HitRecord> {
         let mut temp_rec: HitRecord = HitRecord::new();
         let mut hit_anything: bool = false;
         let mut closest_so_far = t_max;
         for object in &self.objects {
-            let k = object.hit(r, t_min, closest_so_far);
+            let k = object.hit(r, t_min, closest_so_far, rng);
             if let Some(..) = k {
                 temp_rec = k.unwrap();
                 hit_anything = true;
@@ -141,8 +198,29 @@ impl Hittable for HittableList {
         }
         sum
     }
-    fn random(&self, o: &Vec3) -> Vec3 {
-        let int_size = self.objects.len() as i32;
-        self.objects[random_int(0, int_size - 1) as usize].random(o)
+    fn random(&self, o: &Vec3, sampler: &mut dyn Sampler) -> Vec3 {
+        let n = self.objects.len();
+        let k = ((sampler.get_1d() * n as f64) as usize).min(n - 1);
+        self.objects[k].random(o, sampler)
+    }
+    fn area(&self) -> f64 {
+        self.objects.iter().map(|o| o.area()).sum()
+    }
+    //按面积选一个物体，这样整个列表上的点是均匀的，面积 pdf 为 1/area()
+    fn sample_surface(&self, sampler: &mut dyn Sampler) -> Option<(Point3, Vec3)> {
+        let mut x = sampler.get_1d() * self.area();
+        let mut last = None;
+        for object in &self.objects {
+            let a = object.area();
+            if a <= 0.0 {
+                continue;
+            }
+            last = Some(object);
+            if x < a {
+                break;
+            }
+            x -= a;
+        }
+        last?.sample_surface(sampler)
     }
 }
@@ -1,7 +1,9 @@
 use crate::aabb::surrounding_box;
 use crate::aabb::AABB;
 use crate::material::Material;
+use crate::randoms::Rng;
 use crate::ray::Ray;
+use crate::stats;
 use crate::vec3::mul_num;
 use crate::vec3::mul_vec_dot;
 use crate::vec3::Point3;
@@ -27,7 +29,8 @@ impl<M: Clone + Material> MovingSphere<M> {
     }
 }
 impl<M: 'static + Clone + Material> Hittable for MovingSphere<M> {
-    fn hit(&self, r: &Ray, t_min: f64, t_max: f64) -> Option<HitRecord> {
+    fn hit(&self, r: &Ray, t_min: f64, t_max: f64, _rng: &mut Rng) -> Option<HitRecord> {
+        stats::primitive_test();
         let oc: Vec3 = r.orig - self.center(r.time);
         let a = r.dir.length_square();
         let half_b = mul_vec_dot(oc, r.dir);
@@ -3,6 +3,7 @@ use super::rect::XzRect;
 use super::rect::YzRect;
 use crate::aabb::AABB;
 use crate::material::Material;
+use crate::randoms::Rng;
 use crate::ray::Ray;
 use crate::vec3::Point3;
 use crate::HitRecord;
@@ -76,8 +77,8 @@ impl<M: 'static + Clone + Material> MyBox<M> {
     }
 }
 impl<M: Clone + Material> Hittable for MyBox<M> {
-    fn hit(&self, r: &Ray, t_min: f64, t_max: f64) -> Option<HitRecord> {
-        self.sides.hit(r, t_min, t_max)
+    fn hit(&self, r: &Ray, t_min: f64, t_max: f64, rng: &mut Rng) -> Option<HitRecord> {
+        self.sides.hit(r, t_min, t_max, rng)
     }
     fn bounding_box(&self, _time0: f64, _time1: f64, output_box: &mut AABB) -> bool {
         *output_box = AABB {
@@ -0,0 +1,37 @@
+use crate::aabb::AABB;
+use crate::randoms::Rng;
+use crate::ray::Ray;
+use crate::sampler::Sampler;
+use crate::vec3::Point3;
+use crate::vec3::Vec3;
+use crate::HitRecord;
+use crate::Hittable;
+//给击中记录写上物体编号
+pub struct ObjectId<H: Hittable> {
+    pub id: u32,
+    pub ptr: H,
+}
+impl<H: Hittable> Hittable for ObjectId<H> {
+    fn hit(&self, r: &Ray, t_min: f64, t_max: f64, rng: &mut Rng) -> Option<HitRecord> {
+        let k = self.ptr.hit(r, t_min, t_max, rng);
+        k.as_ref()?;
+        let mut rec = k.unwrap();
+        rec.object_id = self.id;
+        Some(rec)
+    }
+    fn bounding_box(&self, time0: f64, time1: f64, output_box: &mut AABB) -> bool {
+        self.ptr.bounding_box(time0, time1, output_box)
+    }
+    fn pdf_value(&self, o: &Point3, v: &Vec3) -> f64 {
+        self.ptr.pdf_value(o, v)
+    }
+    fn random(&self, o: &Vec3, sampler: &mut dyn Sampler) -> Vec3 {
+        self.ptr.random(o, sampler)
+    }
+    fn area(&self) -> f64 {
+        self.ptr.area()
+    }
+    fn sample_surface(&self, sampler: &mut dyn Sampler) -> Option<(Point3, Vec3)> {
+        self.ptr.sample_surface(sampler)
+    }
+}
@@ -1,7 +1,9 @@
 use crate::aabb::AABB;
 use crate::material::Material;
-use crate::randoms::random_double;
+use crate::randoms::Rng;
 use crate::ray::Ray;
+use crate::sampler::Sampler;
+use crate::stats;
 use crate::vec3::mul_vec_dot;
 use crate::vec3::Point3;
 use crate::vec3::Vec3;
@@ -18,7 +20,8 @@ pub struct XyRect<M: Clone + Material> {
     pub mp: M,
 }
 impl<M: Clone + Material + 'static> Hittable for XyRect<M> {
-    fn hit(&self, r: &Ray, t_min: f64, t_max: f64) -> Option<HitRecord> {
+    fn hit(&self, r: &Ray, t_min: f64, t_max: f64, _rng: &mut Rng) -> Option<HitRecord> {
+        stats::primitive_test();
         let t = (self.k - r.orig.e.2) / r.dir.e.2;
         if t < t_min || t > t_max {
             return None;
@@ -58,6 +61,8 @@ impl<M: Clone + Material + 'static> Hittable for XyRect<M> {
             },
             0.001,
             INFINITY,
+            //几何体求交用不到随机数
+            &mut Rng::new(0),
         );
         if k.is_none() {
             return 0.0;
@@ -68,16 +73,28 @@ impl<M: Clone + Material + 'static> Hittable for XyRect<M> {
         let cosine = (mul_vec_dot(*v, rec.normal) / v.length()).abs();
         distance_squared / (cosine * area)
     }
-    fn random(&self, o: &Vec3) -> Vec3 {
+    fn random(&self, o: &Vec3, sampler: &mut dyn Sampler) -> Vec3 {
+        let (s, t) = sampler.get_2d();
         let random_point = Point3 {
             e: (
-                random_double(self.x0, self.x1),
+                self.x0 + s * (self.x1 - self.x0),
+                self.y0 + t * (self.y1 - self.y0),
                 self.k,
-                random_double(self.y0, self.y1),
             ),
         };
         random_point - *o
     }
+    fn area(&self) -> f64 {
+        (self.x1 - self.x0) * (self.y1 - self.y0)
+    }
+    fn sample_surface(&self, sampler: &mut dyn Sampler) -> Option<(Point3, Vec3)> {
+        let (s, t) = sampler.get_2d();
+        let (a, b) = (
+            self.x0 + s * (self.x1 - self.x0),
+            self.y0 + t * (self.y1 - self.y0),
+        );
+        Some((Point3 { e: (a, b, self.k) }, Vec3 { e: (0.0, 0.0, 1.0) }))
+    }
 }
 #[derive(Clone)]
 pub struct XzRect<M: Clone + Material> {
@@ -89,7 +106,8 @@ pub struct XzRect<M: Clone + Material> {
     pub mp: M,
 }
 impl<M: 'static + Clone + Material> Hittable for XzRect<M> {
-    fn hit(&self, r: &Ray, t_min: f64, t_max: f64) -> Option<HitRecord> {
+    fn hit(&self, r: &Ray, t_min: f64, t_max: f64, _rng: &mut Rng) -> Option<HitRecord> {
+        stats::primitive_test();
         let t = (self.k - r.orig.e.1) / r.dir.e.1;
         if t < t_min || t > t_max {
             return None;
@@ -129,6 +147,8 @@ impl<M: 'static + Clone + Material> Hittable for XzRect<M> {
             },
             0.001,
             INFINITY,
+            //几何体求交用不到随机数
+            &mut Rng::new(0),
         );
         if k.is_none() {
             return 0.0;
@@ -139,16 +159,28 @@ impl<M: 'static + Clone + Material> Hittable for XzRect<M> {
         let cosine = (mul_vec_dot(*v, rec.normal) / v.length()).abs();
         distance_squared / (cosine * area)
     }
-    fn random(&self, o: &Vec3) -> Vec3 {
+    fn random(&self, o: &Vec3, sampler: &mut dyn Sampler) -> Vec3 {
+        let (s, t) = sampler.get_2d();
         let random_point = Point3 {
             e: (
-                random_double(self.x0, self.x1),
+                self.x0 + s * (self.x1 - self.x0),
                 self.k,
-                random_double(self.z0, self.z1),
+                self.z0 + t * (self.z1 - self.z0),
             ),
         };
         random_point - *o
     }
+    fn area(&self) -> f64 {
+        (self.x1 - self.x0) * (self.z1 - self.z0)
+    }
+    fn sample_surface(&self, sampler: &mut dyn Sampler) -> Option<(Point3, Vec3)> {
+        let (s, t) = sampler.get_2d();
+        let (a, b) = (
+            self.x0 + s * (self.x1 - self.x0),
+            self.z0 + t * (self.z1 - self.z0),
+        );
+        Some((Point3 { e: (a, self.k, b) }, Vec3 { e: (0.0, 1.0, 0.0) }))
+    }
 }
 #[derive(Clone)]
 pub struct YzRect<M: Clone + Material> {
@@ -160,7 +192,8 @@ pub struct YzRect<M: Clone + Material> {
     pub mp: M,
 }
 impl<M: 'static + Clone + Material> Hittable for YzRect<M> {
-    fn hit(&self, r: &Ray, t_min: f64, t_max: f64) -> Option<HitRecord> {
+    fn hit(&self, r: &Ray, t_min: f64, t_max: f64, _rng: &mut Rng) -> Option<HitRecord> {
+        stats::primitive_test();
         let t = (self.k - r.orig.e.0) / r.dir.e.0;
         if t < t_min || t > t_max {
             return None;
@@ -200,6 +233,8 @@ impl<M: 'static + Clone + Material> Hittable for YzRect<M> {
             },
             0.001,
             INFINITY,
+            //几何体求交用不到随机数
+            &mut Rng::new(0),
         );
         if k.is_none() {
             return 0.0;
@@ -210,14 +245,26 @@ impl<M: 'static + Clone + Material> Hittable for YzRect<M> {
         let cosine = (mul_vec_dot(*v, rec.normal) / v.length()).abs();
         distance_squared / (cosine * area)
     }
-    fn random(&self, o: &Vec3) -> Vec3 {
+    fn random(&self, o: &Vec3, sampler: &mut dyn Sampler) -> Vec3 {
+        let (s, t) = sampler.get_2d();
         let random_point = Point3 {
             e: (
-                random_double(self.y0, self.y1),
                 self.k,
-                random_double(self.z0, self.z1),
+                self.y0 + s * (self.y1 - self.y0),
+                self.z0 + t * (self.z1 - self.z0),
             ),
         };
         random_point - *o
     }
+    fn area(&self) -> f64 {
+        (self.y1 - self.y0) * (self.z1 - self.z0)
+    }
+    fn sample_surface(&self, sampler: &mut dyn Sampler) -> Option<(Point3, Vec3)> {
+        let (s, t) = sampler.get_2d();
+        let (a, b) = (
+            self.y0 + s * (self.y1 - self.y0),
+            self.z0 + t * (self.z1 - self.z0),
+        );
+        Some((Point3 { e: (self.k, a, b) }, Vec3 { e: (1.0, 0.0, 0.0) }))
+    }
 }
@@ -1,5 +1,7 @@
 use crate::aabb::AABB;
+use crate::randoms::Rng;
 use crate::ray::Ray;
+use crate::sampler::Sampler;
 use crate::vec3::Point3;
 use crate::vec3::Vec3;
 use crate::HitRecord;
@@ -47,7 +49,7 @@ impl<H: Hittable> RotateY<H> {
             min.e.1 = crate::randoms::min(min.e.1, tester.e.1);
             max.e.1 = crate::randoms::max(max.e.1, tester.e.1);
             min.e.2 = crate::randoms::min(min.e.2, tester.e.2);
-            max.e.2 = crate::randoms::max(max.e.0, tester.e.2);
+            max.e.2 = crate::randoms::max(max.e.2, tester.e.2);
         }
         Self {
             ptr: p,
@@ -61,8 +63,30 @@ impl<H: Hittable> RotateY<H> {
         }
     }
 }
+impl<H: Hittable> RotateY<H> {
+    //世界坐标转到物体坐标
+    fn to_local(&self, v: &Vec3) -> Vec3 {
+        Vec3 {
+            e: (
+                self.cos_theta * v.e.0 - self.sin_theta * v.e.2,
+                v.e.1,
+                self.sin_theta * v.e.0 + self.cos_theta * v.e.2,
+            ),
+        }
+    }
+    //物体坐标转回世界坐标
+    fn to_world(&self, v: &Vec3) -> Vec3 {
+        Vec3 {
+            e: (
+                self.cos_theta * v.e.0 + self.sin_theta * v.e.2,
+                v.e.1,
+                -self.sin_theta * v.e.0 + self.cos_theta * v.e.2,
+            ),
+        }
+    }
+}
 impl<H: Hittable> Hittable for RotateY<H> {
-    fn hit(&self, r: &Ray, t_min: f64, t_max: f64) -> Option<HitRecord> {
+    fn hit(&self, r: &Ray, t_min: f64, t_max: f64, rng: &mut Rng) -> Option<HitRecord> {
         let mut origin = r.orig;
         let mut direction = r.dir;
         origin.e.0 = self.cos_theta * r.orig.e.0 - self.sin_theta * r.orig.e.2;
@@ -74,7 +98,7 @@ impl<H: Hittable> Hittable for RotateY<H> {
             dir: direction,
             time: r.time,
         };
-        let k = self.ptr.hit(&rotated_r, t_min, t_max);
+        let k = self.ptr.hit(&rotated_r, t_min, t_max, rng);
         k.as_ref()?;
         let mut rec = k.unwrap();
         let mut p = rec.p;
@@ -83,12 +107,27 @@ impl<H: Hittable> Hittable for RotateY<H> {
         p.e.2 = -self.sin_theta * rec.p.e.0 + self.cos_theta * rec.p.e.2;
         normal.e.0 = self.cos_theta * rec.normal.e.0 + self.sin_theta * rec.normal.e.2;
         normal.e.2 = -self.sin_theta * rec.normal.e.0 + self.cos_theta * rec.normal.e.2;
+        //法线已经朝向光线来的一侧，转回来即可，击中的是哪一面不变
         rec.p = p;
-        rec.set_face_normal(&rotated_r, normal);
+        rec.normal = normal;
         Some(rec)
     }
     fn bounding_box(&self, _time0: f64, _time1: f64, output_box: &mut AABB) -> bool {
         *output_box = self.bbox;
         self.hasbox
     }
+    //作为光源时观察点与方向都转到物体坐标，取到的方向再转回来
+    fn pdf_value(&self, o: &Point3, v: &Vec3) -> f64 {
+        self.ptr.pdf_value(&self.to_local(o), &self.to_local(v))
+    }
+    fn random(&self, o: &Vec3, sampler: &mut dyn Sampler) -> Vec3 {
+        self.to_world(&self.ptr.random(&self.to_local(o), sampler))
+    }
+    fn area(&self) -> f64 {
+        self.ptr.area()
+    }
+    fn sample_surface(&self, sampler: &mut dyn Sampler) -> Option<(Point3, Vec3)> {
+        let (p, n) = self.ptr.sample_surface(sampler)?;
+        Some((self.to_world(&p), self.to_world(&n)))
+    }
 }
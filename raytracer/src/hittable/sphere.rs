@@ -1,7 +1,9 @@
 use crate::aabb::AABB;
 use crate::material::Material;
-use crate::randoms::random_to_sphere;
+use crate::randoms::{sample_sphere, sample_to_sphere, Rng};
 use crate::ray::Ray;
+use crate::sampler::Sampler;
+use crate::stats;
 use crate::vec3::mul_vec_dot;
 use crate::vec3::Onb;
 use crate::vec3::Point3;
@@ -26,7 +28,8 @@ impl<M: 'static + Clone + Material> Sphere<M> {
     }
 }
 impl<M: 'static + Clone + Material> Hittable for Sphere<M> {
-    fn hit(&self, r: &Ray, t_min: f64, t_max: f64) -> Option<HitRecord> {
+    fn hit(&self, r: &Ray, t_min: f64, t_max: f64, _rng: &mut Rng) -> Option<HitRecord> {
+        stats::primitive_test();
         let oc: Vec3 = r.orig - self.center;
         let a = r.dir.length_square();
         let half_b = mul_vec_dot(oc, r.dir);
@@ -75,6 +78,8 @@ impl<M: 'static + Clone + Material> Hittable for Sphere<M> {
             },
             0.001,
             INFINITY,
+            //几何体求交用不到随机数
+            &mut Rng::new(0),
         );
         if k.is_none() {
             return 0.0;
@@ -84,10 +89,21 @@ impl<M: 'static + Clone + Material> Hittable for Sphere<M> {
         let solid_angle = 2.0 * PI * (1.0 - cos_theta_max);
         1.0 / solid_angle
     }
-    fn random(&self, o: &Point3) -> Vec3 {
+    fn random(&self, o: &Point3, sampler: &mut dyn Sampler) -> Vec3 {
         let direction = self.center - *o;
         let distance_squared = direction.length_square();
         let uvw = Onb::build_from_w(&direction);
-        uvw.local_vec(&random_to_sphere(self.radius, distance_squared))
+        uvw.local_vec(&sample_to_sphere(
+            self.radius,
+            distance_squared,
+            sampler.get_2d(),
+        ))
+    }
+    fn area(&self) -> f64 {
+        4.0 * PI * self.radius * self.radius
+    }
+    fn sample_surface(&self, sampler: &mut dyn Sampler) -> Option<(Point3, Vec3)> {
+        let n = sample_sphere(sampler.get_2d());
+        Some((self.center + n * self.radius, n))
     }
 }
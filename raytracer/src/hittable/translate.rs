@@ -1,6 +1,8 @@
 use crate::aabb::AABB;
+use crate::randoms::Rng;
 use crate::ray::Ray;
-use crate::vec3::Vec3;
+use crate::sampler::Sampler;
+use crate::vec3::{Point3, Vec3};
 use crate::HitRecord;
 use crate::Hittable;
 #[derive(Clone)]
@@ -9,17 +11,17 @@ pub struct Translate<H: Hittable> {
     pub ptr: H,
 }
 impl<H: Hittable> Hittable for Translate<H> {
-    fn hit(&self, r: &Ray, t_min: f64, t_max: f64) -> Option<HitRecord> {
+    fn hit(&self, r: &Ray, t_min: f64, t_max: f64, rng: &mut Rng) -> Option<HitRecord> {
         let moved_r = Ray {
             orig: r.orig - self.offset,
             dir: r.dir,
             time: r.time,
         };
-        let k = self.ptr.hit(&moved_r, t_min, t_max);
+        let k = self.ptr.hit(&moved_r, t_min, t_max, rng);
         k.as_ref()?;
         let mut rec = k.unwrap();
+        //平移不改变法线，也不改变击中的是哪一面
         rec.p += self.offset;
-        rec.set_face_normal(&moved_r, rec.normal);
         Some(rec)
     }
     fn bounding_box(&self, time0: f64, time1: f64, output_box: &mut AABB) -> bool {
@@ -32,4 +34,18 @@ impl<H: Hittable> Hittable for Translate<H> {
         };
         true
     }
+    //作为光源时把观察点移回物体坐标，方向不变
+    fn pdf_value(&self, o: &Point3, v: &Vec3) -> f64 {
+        self.ptr.pdf_value(&(*o - self.offset), v)
+    }
+    fn random(&self, o: &Vec3, sampler: &mut dyn Sampler) -> Vec3 {
+        self.ptr.random(&(*o - self.offset), sampler)
+    }
+    fn area(&self) -> f64 {
+        self.ptr.area()
+    }
+    fn sample_surface(&self, sampler: &mut dyn Sampler) -> Option<(Point3, Vec3)> {
+        let (p, n) = self.ptr.sample_surface(sampler)?;
+        Some((p + self.offset, n))
+    }
 }
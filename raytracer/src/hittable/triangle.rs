@@ -2,7 +2,9 @@ use crate::aabb::AABB;
 use crate::material::Material;
 use crate::randoms::max;
 use crate::randoms::min;
+use crate::randoms::Rng;
 use crate::ray::Ray;
+use crate::stats;
 use crate::vec3::mul_vec_cross;
 use crate::vec3::mul_vec_dot;
 use crate::vec3::Point3;
@@ -22,7 +24,8 @@ pub struct Triangle<M: Clone + Material> {
 }
 //暂时不能发光
 impl<M: Clone + Material> Hittable for Triangle<M> {
-    fn hit(&self, r: &Ray, t_min: f64, t_max: f64) -> Option<HitRecord> {
+    fn hit(&self, r: &Ray, t_min: f64, t_max: f64, _rng: &mut Rng) -> Option<HitRecord> {
+        stats::primitive_test();
         let oa = self.a - r.orig;
         let t = mul_vec_dot(oa, self.n) / mul_vec_dot(r.dir, self.n);
         if t < t_min || t > t_max {
@@ -41,6 +44,7 @@ impl<M: Clone + Material> Hittable for Triangle<M> {
                 mat_ptr: &self.mp,
                 u: self.uva.0 + v * self.uvab.0 + u * self.uvac.0,
                 v: self.uva.1 + v * self.uvab.1 + u * self.uvac.1,
+                object_id: 0,
             };
             Some(rec)
         } else {
@@ -0,0 +1,373 @@
+use std::f64::{consts::PI, INFINITY};
+
+use crate::{
+    hittable::{HitRecord, Hittable, HittableList},
+    pdf::{HittablePdf, MixturePdf, Pdf},
+    photonmap::PhotonMap,
+    randoms::{sample_cosine_direction, Rng},
+    ray::Ray,
+    sampler::Sampler,
+    spectrum::{set_hero_wavelength, terminate_secondary, SampledWavelengths},
+    stats,
+    vec3::{mul_vec_dot, Color, Onb, Point3, Vec3},
+};
+
+#[derive(Clone, Copy, PartialEq)]
+pub enum Heuristic {
+    Balance,
+    Power,
+}
+
+#[derive(Clone, Copy)]
+pub struct Integrator {
+    pub max_depth: i32,
+    //弹射这么多次之后才开始俄罗斯轮盘赌
+    pub rr_depth: i32,
+    //None 时按 light_weight 混合光源与材质的 pdf 只采样一次，否则每次弹射各采样一次并用 MIS 加权
+    pub heuristic: Option<Heuristic>,
+    pub light_weight: f64,
+    //为真时用双向路径追踪（bdpt.rs）代替 ray_color
+    pub bidirectional: bool,
+    //为真时每条路径采样波长，按光谱渲染（spectrum.rs）
+    pub spectral: bool,
+}
+
+//相机光线第一次击中处的信息，供 AOV 使用
+#[derive(Clone, Copy)]
+pub struct FirstHit {
+    pub p: Point3,
+    pub shading_normal: Vec3,
+    pub geometric_normal: Vec3,
+    pub albedo: Color,
+    pub t: f64,
+    pub u: f64,
+    pub v: f64,
+    pub object_id: u32,
+    pub material_id: u32,
+}
+
+//一条路径的结果，emitted 为其中第一次击中处的自发光（没击中时为背景）
+pub struct PathSample {
+    pub radiance: Color,
+    pub emitted: Color,
+    pub first_hit: Option<FirstHit>,
+}
+
+//材质采样得到当前光线时的起点与 pdf，用来给击中光源时的自发光加权
+#[derive(Clone, Copy)]
+struct BsdfSample {
+    origin: Point3,
+    pdf: f64,
+}
+
+//光源表面上发出的一条光线：起点、发光一侧的法线、辐亮度、方向与方向的立体角 pdf（余弦分布）
+pub struct Emission {
+    pub p: Point3,
+    pub n: Vec3,
+    pub le: Color,
+    pub dir: Vec3,
+    pub pdf_dir: f64,
+}
+
+//在 lights 的表面上按面积均匀取一点（面积 pdf 为 1/total_area），从两侧探测 world 找出发光的一侧，
+//再在该侧按余弦分布取出射方向；bdpt 的光源子路径与光子发射共用
+pub fn sample_emission<H: Hittable>(
+    lights: &HittableList,
+    world: &H,
+    total_area: f64,
+    time: f64,
+    sampler: &mut dyn Sampler,
+    rng: &mut Rng,
+) -> Option<Emission> {
+    if total_area <= 0.0 {
+        return None;
+    }
+    let (p, n) = lights.sample_surface(sampler)?;
+    let eps = 1e-4 * (1.0 + p.e.0.abs().max(p.e.1.abs()).max(p.e.2.abs()));
+    let u = sampler.get_2d();
+    for side in [n, -n] {
+        let probe = Ray {
+            orig: p + side * eps,
+            dir: -side,
+            time,
+        };
+        if let Some(rec) = world.hit(&probe, 0.0, 2.0 * eps, rng) {
+            let le = rec.mat_ptr.emitted(&probe, &rec, rec.u, rec.v, &rec.p);
+            if le.e.0 <= 0.0 && le.e.1 <= 0.0 && le.e.2 <= 0.0 {
+                continue;
+            }
+            let dir = Onb::build_from_w(&side).local_vec(&sample_cosine_direction(u));
+            let cos = mul_vec_dot(dir.unit_vector(), side);
+            if cos <= 0.0 {
+                return None;
+            }
+            return Some(Emission {
+                p,
+                n: side,
+                le,
+                dir,
+                pdf_dir: cos / PI,
+            });
+        }
+    }
+    None
+}
+
+impl Integrator {
+    fn mis_weight(&self, pdf_light: f64, pdf_bsdf: f64) -> f64 {
+        let a = self.light_weight * pdf_light;
+        let b = (1.0 - self.light_weight) * pdf_bsdf;
+        if a + b <= 0.0 {
+            return 0.0;
+        }
+        match self.heuristic {
+            Some(Heuristic::Power) => a * a / (a * a + b * b),
+            _ => a / (a + b),
+        }
+    }
+    //光线在 t 处击中的是否就是 lights 里的光源
+    pub(crate) fn hits_light(lights: &HittableList, r: &Ray, t: f64, rng: &mut Rng) -> bool {
+        match lights.hit(r, 0.001, INFINITY, rng) {
+            Some(rec) => (rec.t - t).abs() <= 1e-6 * t.max(1.0),
+            None => false,
+        }
+    }
+    //沿路径迭代，throughput 记录路径到目前为止的权重，弹射 rr_depth 次后按 throughput 做俄罗斯轮盘赌；
+    //给了焦散光子图时，在非镜面处加上光子的密度估计，并不再计入 漫反射→镜面…→光源 的自发光；
+    //给了波长时按光谱渲染，材质与光源的 RGB 先换成这几个波长上的值，结果再换回 RGB
+    #[allow(clippy::too_many_arguments)]
+    pub fn ray_color<H: Hittable>(
+        &self,
+        r: &Ray,
+        lights: &HittableList,
+        background: Color,
+        world: &H,
+        sampler: &mut dyn Sampler,
+        rng: &mut Rng,
+        photons: Option<&PhotonMap>,
+        wavelengths: Option<&SampledWavelengths>,
+    ) -> PathSample {
+        let lift = |c: Color| match wavelengths {
+            Some(w) => w.lift(&c),
+            None => c,
+        };
+        set_hero_wavelength(wavelengths.map(|w| w.lambda[0]));
+        let mut secondary_terminated = false;
+        let mut radiance = Color::new();
+        let mut first_emitted = Color::new();
+        let mut first_hit = None;
+        let mut throughput = Color { e: (1.0, 1.0, 1.0) };
+        let mut ray = *r;
+        let mut prev: Option<BsdfSample> = None;
+        //路径上出现过非镜面顶点，以及当前光线是否在它之后只经过了镜面
+        let mut diffuse_seen = false;
+        let mut caustic = false;
+        let mut depth = 0;
+        while depth < self.max_depth {
+            if depth == 0 {
+                stats::primary_ray();
+            } else {
+                stats::secondary_ray();
+            }
+            let w = world.hit(&ray, 0.001, INFINITY, rng);
+            if w.is_none() {
+                radiance += throughput * lift(background);
+                if depth == 0 {
+                    first_emitted = background;
+                }
+                break;
+            }
+            let rec = w.unwrap();
+            let mut emitted = rec.mat_ptr.emitted(&ray, &rec, rec.u, rec.v, &rec.p);
+            if caustic && photons.is_some() {
+                emitted = Color::new();
+            }
+            if let Some(prev) = prev {
+                if emitted.length_square() > 0.0 && Self::hits_light(lights, &ray, rec.t, rng) {
+                    let pdf_light = lights.pdf_value(&prev.origin, &ray.dir);
+                    emitted = emitted * (1.0 - self.mis_weight(pdf_light, prev.pdf));
+                }
+            }
+            radiance += throughput * lift(emitted);
+            if depth == 0 {
+                first_emitted = emitted;
+                first_hit = Some(FirstHit {
+                    p: rec.p,
+                    shading_normal: rec.normal,
+                    geometric_normal: if rec.front_face {
+                        rec.normal
+                    } else {
+                        -rec.normal
+                    },
+                    albedo: rec.mat_ptr.albedo(&rec),
+                    t: rec.t,
+                    u: rec.u,
+                    v: rec.v,
+                    object_id: rec.object_id,
+                    material_id: rec.mat_ptr.id(),
+                });
+            }
+            let k = rec.mat_ptr.scatter(&ray, &rec, sampler.get_1d());
+            if k.is_none() {
+                break;
+            }
+            let srec = k.unwrap();
+            let attenuation = lift(srec.attenuation);
+            if srec.dispersive && wavelengths.is_some() && !secondary_terminated {
+                throughput = terminate_secondary(&throughput);
+                secondary_terminated = true;
+            }
+            if srec.is_specular {
+                throughput = throughput * attenuation;
+                ray = srec.specular_ray;
+                prev = None;
+                caustic = diffuse_seen;
+            } else {
+                if let Some(map) = photons {
+                    radiance += throughput * map.estimate(&ray, &rec, &srec.attenuation);
+                }
+                diffuse_seen = true;
+                caustic = false;
+                let bsdf_pdf = srec.pdf_ptr.unwrap();
+                //场景没有声明光源时只按材质采样
+                if lights.objects.is_empty() || self.heuristic.is_none() {
+                    let (dir, pdf_val) = if lights.objects.is_empty() {
+                        let dir = bsdf_pdf.generate(sampler);
+                        (dir, bsdf_pdf.value(&dir))
+                    } else {
+                        let p = MixturePdf {
+                            p1: &HittablePdf {
+                                o: rec.p,
+                                ptr: lights,
+                            },
+                            p2: bsdf_pdf.as_ref(),
+                            weight: self.light_weight,
+                        };
+                        let dir = p.generate(sampler);
+                        (dir, p.value(&dir))
+                    };
+                    if pdf_val <= 0.0 {
+                        break;
+                    }
+                    let mut scattered = Ray {
+                        orig: rec.p,
+                        dir,
+                        time: ray.time,
+                    };
+                    //采样方向落在表面以下时这条路径已经没有贡献，不再往下追踪
+                    let f = attenuation * rec.mat_ptr.scattering_pdf(&ray, &rec, &mut scattered);
+                    if f.length_square() <= 0.0 {
+                        break;
+                    }
+                    throughput = throughput * f / pdf_val;
+                    ray = scattered;
+                    prev = None;
+                } else {
+                    radiance += throughput
+                        * self.direct_light(
+                            &ray,
+                            &rec,
+                            &attenuation,
+                            bsdf_pdf.as_ref(),
+                            lights,
+                            world,
+                            sampler,
+                            rng,
+                            wavelengths,
+                        );
+                    //材质采样：继续追踪路径，击中光源时的自发光在下一次循环按权重计入
+                    let dir = bsdf_pdf.generate(sampler);
+                    let pdf_bsdf = bsdf_pdf.value(&dir);
+                    if pdf_bsdf <= 0.0 {
+                        break;
+                    }
+                    let mut scattered = Ray {
+                        orig: rec.p,
+                        dir,
+                        time: ray.time,
+                    };
+                    let f = attenuation * rec.mat_ptr.scattering_pdf(&ray, &rec, &mut scattered);
+                    if f.length_square() <= 0.0 {
+                        break;
+                    }
+                    throughput = throughput * f / pdf_bsdf;
+                    ray = scattered;
+                    prev = Some(BsdfSample {
+                        origin: rec.p,
+                        pdf: pdf_bsdf,
+                    });
+                }
+            }
+            depth += 1;
+            if depth >= self.rr_depth {
+                let q = throughput
+                    .e
+                    .0
+                    .max(throughput.e.1)
+                    .max(throughput.e.2)
+                    .min(0.95);
+                if q <= 0.0 || sampler.get_1d() >= q {
+                    break;
+                }
+                throughput = throughput / q;
+            }
+        }
+        if let Some(w) = wavelengths {
+            radiance = w.to_rgb(&radiance);
+            set_hero_wavelength(None);
+        }
+        PathSample {
+            radiance,
+            emitted: first_emitted,
+            first_hit,
+        }
+    }
+    //光源采样：从 rec 直接连向光源，按 MIS 权重计入
+    #[allow(clippy::too_many_arguments)]
+    fn direct_light<H: Hittable>(
+        &self,
+        r: &Ray,
+        rec: &HitRecord,
+        attenuation: &Color,
+        bsdf_pdf: &dyn Pdf,
+        lights: &HittableList,
+        world: &H,
+        sampler: &mut dyn Sampler,
+        rng: &mut Rng,
+        wavelengths: Option<&SampledWavelengths>,
+    ) -> Color {
+        let light_dir: Vec3 = lights.random(&rec.p, sampler);
+        let pdf_light = lights.pdf_value(&rec.p, &light_dir);
+        if pdf_light <= 0.0 {
+            return Color::new();
+        }
+        let mut shadow = Ray {
+            orig: rec.p,
+            dir: light_dir,
+            time: r.time,
+        };
+        let f = *attenuation * rec.mat_ptr.scattering_pdf(r, rec, &mut shadow);
+        if f.length_square() <= 0.0 {
+            return Color::new();
+        }
+        stats::shadow_ray();
+        match world.hit(&shadow, 0.001, INFINITY, rng) {
+            Some(light_rec) if Self::hits_light(lights, &shadow, light_rec.t, rng) => {
+                let le = light_rec.mat_ptr.emitted(
+                    &shadow,
+                    &light_rec,
+                    light_rec.u,
+                    light_rec.v,
+                    &light_rec.p,
+                );
+                let le = match wavelengths {
+                    Some(w) => w.lift(&le),
+                    None => le,
+                };
+                let weight = self.mis_weight(pdf_light, bsdf_pdf.value(&light_dir));
+                f * le * (weight / pdf_light)
+            }
+            _ => Color::new(),
+        }
+    }
+}
@@ -1,21 +1,45 @@
-use std::path::Path;
+use std::{path::Path, sync::Arc, thread};
 
 use tobj::{load_obj, LoadOptions};
 
 use crate::{
-    bvh::BvhNode,
+    bvh::{available_threads, BvhNode},
     hittable::triangle::Triangle,
     hittable::{Hittable, HittableList},
-    material::lambertian::Lambertian,
+    material::{lambertian::Lambertian, WithId},
     texture::objloadingtexture::ObjLoadingTexture,
     vec3::Point3,
 };
-pub fn obj_mtl_load(name: &String) -> HittableList {
-    let mut my_model = HittableList::new();
+//把 items 按顺序均分给各线程，结果仍按 items 的顺序
+fn parallel_map<T, R, F>(items: Vec<T>, f: F) -> Vec<R>
+where
+    T: Send + 'static,
+    R: Send + 'static,
+    F: Fn(T) -> R + Send + Sync + 'static,
+{
+    let threads = available_threads().min(items.len()).max(1);
+    let chunk = (items.len() + threads - 1) / threads;
+    let f = Arc::new(f);
+    let mut items = items.into_iter();
+    let handles: Vec<_> = (0..threads)
+        .map(|_| {
+            let part: Vec<T> = items.by_ref().take(chunk).collect();
+            let f = f.clone();
+            thread::spawn(move || part.into_iter().map(|x| f(x)).collect::<Vec<R>>())
+        })
+        .collect();
+    handles
+        .into_iter()
+        .flat_map(|h| h.join().unwrap())
+        .collect()
+}
+//raytracer/objects/<name>/<name>.obj 里所有的三角形；贴图的解码与各网格三角形的建立分给多个线程。
+//模型、材质库或贴图读不出来，或者网格没有材质时返回错误
+pub fn obj_triangles(name: &str) -> Result<Vec<Box<dyn Hittable>>, String> {
     let path = format!("raytracer/objects/{}", name);
     let project = format!("{}/{}.obj", path, name);
     let (models, materials) = load_obj(
-        project,
+        &project,
         &LoadOptions {
             single_index: (false),
             triangulate: (true),
@@ -23,68 +47,82 @@ pub fn obj_mtl_load(name: &String) -> HittableList {
             ignore_lines: (true),
         },
     )
-    .expect("failed");
-    let m = materials.unwrap();
-    let mut textures: Vec<ObjLoadingTexture> = Vec::new();
-
-    for mat in m {
+    .map_err(|e| format!("cannot load {}: {}", project, e))?;
+    let m = materials.map_err(|e| format!("cannot load the materials of {}: {}", project, e))?;
+    let textures = parallel_map(m, move |mat| {
         if let Some(texture_name) = mat.diffuse_texture {
-            let path = format!("raytracer/objects/{}", name);
             let texture_path = format!("{}/{}", path, texture_name);
-            textures.push(ObjLoadingTexture::new(Path::new(&texture_path)));
+            ObjLoadingTexture::new(Path::new(&texture_path))
         } else if let Some(solid_texture) = mat.diffuse {
-            textures.push(ObjLoadingTexture::new_solid_color((
+            Ok(ObjLoadingTexture::new_solid_color((
                 solid_texture[0],
                 solid_texture[1],
                 solid_texture[2],
             )))
         } else {
-            textures.push(ObjLoadingTexture::new_solid_color((0.0, 0.0, 0.0)));
+            Ok(ObjLoadingTexture::new_solid_color((0.0, 0.0, 0.0)))
         }
-    }
-    for mo in models {
+    });
+    let textures = Arc::new(textures.into_iter().collect::<Result<Vec<_>, _>>()?);
+    let meshes = parallel_map(models, move |mo| {
         let pos = mo.mesh.positions;
         let ind = mo.mesh.indices;
         let tex = mo.mesh.texcoords;
         let tex_ind = mo.mesh.texcoord_indices;
         let mut points = Vec::new();
-        let mut hittable_mo: Vec<Box<dyn Hittable>> = Vec::new();
+        let mut triangles = Vec::new();
         for i in 0..pos.len() / 3 {
             points.push(Point3 {
                 e: (pos[i * 3], pos[i * 3 + 1], pos[i * 3 + 2]),
             });
         }
-        let texture_mo = textures[mo.mesh.material_id.unwrap()].clone();
+        let (material_id, texture_mo) = match mo
+            .mesh
+            .material_id
+            .and_then(|id| Some((id, textures.get(id)?)))
+        {
+            Some((id, t)) => (id, t.clone()),
+            None => return Err(format!("mesh {} has no material", mo.name)),
+        };
         let mut uv = [(0.0, 0.0), (0.0, 1.0), (1.0, 0.0)];
         for i in 0..ind.len() / 3 {
-            for j in 0..3 {
-                let index = tex_ind[i * 3 + j] as usize;
-                uv[j] = (tex[index << 1], tex[(index << 1) | 1]);
+            //没有贴图坐标时用默认的 uv
+            if tex_ind.len() >= ind.len() {
+                for j in 0..3 {
+                    let index = tex_ind[i * 3 + j] as usize;
+                    uv[j] = (tex[index << 1], tex[(index << 1) | 1]);
+                }
             }
-            hittable_mo.push(Box::new(Triangle::new(
+            triangles.push(Triangle::new(
                 points[ind[i * 3] as usize],
                 points[ind[i * 3 + 1] as usize],
                 points[ind[i * 3 + 2] as usize],
-                Lambertian {
-                    albedo: texture_mo.clone(),
+                //材质编号按 .mtl 里的顺序从 1 开始
+                WithId {
+                    id: material_id as u32 + 1,
+                    mat: Lambertian {
+                        albedo: texture_mo.clone(),
+                    },
                 },
                 uv[0],
                 uv[1],
                 uv[2],
-            )))
+            ))
         }
-        let end = hittable_mo.len() as u32;
-        my_model.add(Box::new(BvhNode::new_nodes(
-            &mut hittable_mo,
-            0,
-            end,
-            0.0,
-            0.0,
-        )));
+        Ok(triangles)
+    });
+    let mut triangles: Vec<Box<dyn Hittable>> = Vec::new();
+    for mesh in meshes {
+        triangles.extend(mesh?.into_iter().map(|t| Box::new(t) as Box<dyn Hittable>));
     }
-    let end = my_model.objects.len() as u32;
-    let bvh = BvhNode::new_nodes(&mut my_model.objects, 0, end, 0.0, 0.0);
+    Ok(triangles)
+}
+//整个模型的三角形放在一棵 BVH 里
+pub fn obj_mtl_load(name: &str) -> Result<HittableList, String> {
+    let mut triangles = obj_triangles(name)?;
+    let end = triangles.len() as u32;
+    let bvh = BvhNode::new_nodes(&mut triangles, 0, end, 0.0, 0.0);
     let mut ans = HittableList::new();
     ans.add(Box::new(bvh));
-    ans
+    Ok(ans)
 }
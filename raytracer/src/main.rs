@@ -1,185 +1,995 @@
 pub mod aabb;
+pub mod animation;
+pub mod aov;
+pub mod bdpt;
 pub mod bvh;
 pub mod camera;
+pub mod cli;
+pub mod denoise;
 pub mod edgedetect;
+pub mod film;
+pub mod framebuffer;
 pub mod hittable;
+pub mod integrator;
 pub mod loadobj;
 pub mod material;
+pub mod output;
 pub mod pdf;
 pub mod perlin;
+pub mod photonmap;
 pub mod randoms;
 pub mod ray;
+pub mod sampler;
 pub mod scene;
+pub mod spectrum;
+pub mod stats;
 pub mod texture;
+pub mod tile;
+pub mod tonemap;
 pub mod vec3;
+use aov::{Aov, AovBuffer};
+use clap::Parser;
+use cli::{BvhArgs, Cli, Command, RenderArgs};
 use console::style;
+use denoise::{Denoiser, Guides};
 use edgedetect::edgedetect;
+use film::{Filter, FILTER_NAMES};
+use framebuffer::FrameBuffer;
 use hittable::HitRecord;
 use hittable::HittableList;
-use image::{ImageBuffer, RgbImage};
-use indicatif::MultiProgress;
 use indicatif::ProgressBar;
-//use pdf::HittablePdf;
-use pdf::MixturePdf;
-use pdf::Pdf;
-use std::f64::INFINITY;
-use std::ops::AddAssign;
+use integrator::{Heuristic, Integrator};
+use output::{write_image, OutputFormat};
+use std::sync::atomic::{AtomicBool, Ordering};
 use std::sync::Arc;
-use std::sync::Mutex;
 use std::thread;
-use std::{fs::File, process::exit};
+use std::time::Instant;
+use std::{
+    path::{Path, PathBuf},
+    process::exit,
+};
+use tonemap::ToneMapper;
 
+use crate::aabb::AABB;
+use crate::bdpt::CameraView;
 use crate::bvh::BvhNode;
+use crate::camera::Camera;
+use crate::hittable::objectid::ObjectId;
 use crate::hittable::Hittable;
-#[allow(unused_imports)]
+use crate::loadobj::obj_triangles;
+use crate::photonmap::{trace_photons, PhotonMap};
 use crate::scene::{
-    cornellbox::cornell_box, cornellboxsmoke::cornell_box_smoke, earth::earth,
-    finalscene::final_scene, myworld::my_world, randomscene::random_scene,
-    simplelight::simple_light, twoperlinsphere::two_perlin_sphere, twosphere::two_spheres,
+    builtin_scene,
+    scenefile::{build_scene, parse_scene, SceneCache},
+    BUILTIN_SCENES,
 };
+use crate::spectrum::SampledWavelengths;
+use crate::stats::RayStats;
+use crate::tile::{TileBuffer, TileOrder, TileQueue};
+use crate::vec3::{Color, Point3};
 
-//use crate::hittable::rect::XzRect;
-//use crate::hittable::sphere::Sphere;
-//use crate::material::diffuselight::DiffuseLight;
-//use crate::randoms::final_scene;
-use crate::randoms::random_double;
-use crate::ray::write_color;
+use crate::randoms::{sample_seed, Rng};
 use crate::ray::Ray;
-use crate::vec3::Color;
+use crate::sampler::{make_sampler, SAMPLER_NAMES};
+
+//载入并建好 BVH 的一帧：背景、宽高比、宽、场景、光源、相机
+type Scene = (Color, f64, u32, BvhNode, HittableList, Camera);
 
-fn ray_color(
-    r: &Ray,
-    lights: &HittableList,
+//焦散光子图的设置，radius 为 None 时按场景大小取
+#[derive(Clone, Copy)]
+struct PhotonSettings {
+    count: u32,
+    radius: Option<f64>,
+    progressive: bool,
+    alpha: f64,
+}
+
+//与帧无关的设置
+struct Settings {
+    format: OutputFormat,
+    tone: ToneMapper,
+    requested: Vec<Aov>,
+    aovs: Vec<Aov>,
+    denoiser: Option<Denoiser>,
+    integrator: Integrator,
+    photons: Option<PhotonSettings>,
+    tile_order: TileOrder,
+    filter: Filter,
+    width: u32,
+    height: u32,
     background: Color,
-    world: &BvhNode,
-    depth: i32,
-) -> Color {
-    let rec: HitRecord;
-    if depth <= 0 {
-        return Color { e: (0.0, 0.0, 0.0) };
-    }
-    let w = world.hit(r, 0.001, INFINITY);
-    if let Some(..) = w {
-        rec = w.unwrap();
-        let mut scattered: Ray;
-        let emitted = rec.mat_ptr.emitted(r, &rec, rec.u, rec.v, &rec.p);
-        let k = rec.mat_ptr.scatter(r, &rec);
-        if let Some(..) = k {
-            let srec = k.unwrap();
-            if srec.is_specular {
-                return srec.attenuation
-                    * ray_color(&srec.specular_ray, lights, background, world, depth - 1);
+    stop: Arc<AtomicBool>,
+}
+
+//一帧的场景与输出位置
+struct Frame {
+    path: PathBuf,
+    checkpoint: String,
+    spp_map: Option<String>,
+    cost_map: Option<String>,
+    scene_name: String,
+    resume: bool,
+    world: Arc<BvhNode>,
+    lights: Arc<HittableList>,
+    cam: Camera,
+}
+
+fn render(args: RenderArgs) {
+    let path = Path::new(&args.output);
+    let format = match OutputFormat::from_path(path, &args.format, args.quality) {
+        Some(f) => f,
+        None => {
+            println!(
+                "{}",
+                style("Unknown output format, use exr, hdr, pfm, png, jpeg, ppm or bmp.").red()
+            );
+            exit(1);
+        }
+    };
+    let tone = match ToneMapper::from_name(&args.tonemap, args.exposure, args.white_point) {
+        Some(t) => t,
+        None => {
+            println!(
+                "{}",
+                style("Unknown tone mapping, use linear, reinhard, aces or hable.").red()
+            );
+            exit(1);
+        }
+    };
+    let requested = match Aov::parse_list(&args.aov) {
+        Ok(a) => a,
+        Err(e) => {
+            println!("{}", style(e).red());
+            exit(1);
+        }
+    };
+    //降噪需要的引导图即使没要求输出也要记录
+    let mut aovs = requested.clone();
+    let denoiser = if args.denoise {
+        for a in [Aov::Albedo, Aov::ShadingNormal, Aov::Depth] {
+            if !aovs.contains(&a) {
+                aovs.push(a);
             }
-            let p2_ = srec.pdf_ptr.unwrap();
-            let p = {
-                MixturePdf {
-                    //切换的时候要改一下
-                    /*p1: &HittablePdf {
-                        o: rec.p,
-                        ptr: lights,
-                    },*/
-                    p1: p2_.as_ref(),
-                    p2: p2_.as_ref(),
-                }
-            };
-            scattered = Ray {
-                orig: rec.p,
-                dir: p.generate(),
-                time: r.time,
+        }
+        Some(Denoiser {
+            iterations: args.denoise_iterations,
+            strength: args.denoise_strength as f32,
+        })
+    } else {
+        None
+    };
+    let scene_file = args.scene_file.as_ref().map(|file| {
+        parse_scene(Path::new(file)).unwrap_or_else(|e| {
+            println!("{}", style(e).red());
+            exit(1);
+        })
+    });
+    //没给帧范围时，有关键帧的场景渲染关键帧覆盖的所有帧，否则只渲染一张不带帧号的图
+    let key_range = scene_file.as_ref().and_then(|f| f.frame_range());
+    let frames = if args.frame_start.is_none() && args.frame_end.is_none() {
+        key_range
+    } else {
+        let start = args.frame_start.or(key_range.map(|r| r.0)).unwrap_or(0);
+        let end = args.frame_end.or(key_range.map(|r| r.1)).unwrap_or(start);
+        if end < start {
+            println!(
+                "{}",
+                style("The last frame comes before the first frame.").red()
+            );
+            exit(1);
+        }
+        Some((start, end))
+    };
+    //场景生成（随机场景、Perlin 噪声）从全局种子开始，每帧重建时也一样；
+    //载入与建 BVH 的用时分开报告，场景里嵌套的 BVH（OBJ 模型等）算在建 BVH 里；
+    //previous 为上一帧的 BVH 时沿用它的拓扑；cache 里是前面各帧建好的不动的物体
+    let load_frame = |frame: u32, previous: Option<BvhNode>, cache: &mut SceneCache| -> Scene {
+        let start = Instant::now();
+        let bvh_before = bvh::build_seconds();
+        let mut scene_rng = Rng::new(sample_seed(args.seed, u64::MAX, 0));
+        let scene = match &scene_file {
+            Some(file) => build_scene(file, frame as f64, &mut scene_rng, cache),
+            None => builtin_scene(&args.scene, &mut scene_rng).ok_or(format!(
+                "unknown scene \"{}\", see `list-scenes`",
+                args.scene
+            )),
+        };
+        let (background, aspect_ratio, width, world, lights, cam) = match scene {
+            Ok(s) => s,
+            Err(e) => {
+                println!("{}", style(e).red());
+                exit(1);
+            }
+        };
+        let world = match previous {
+            Some(bvh) => refit_bvh(bvh, world),
+            None => build_bvh(world),
+        };
+        let bvh_time = bvh::build_seconds() - bvh_before;
+        println!(
+            "Scene loaded in {:.3}s, BVH built in {:.3}s",
+            start.elapsed().as_secs_f64() - bvh_time,
+            bvh_time
+        );
+        if cache.reused > 0 {
+            println!(
+                "Reused {} unchanged objects, saving {:.3}s of loading and BVH building",
+                cache.reused, cache.saved_seconds
+            );
+        }
+        (background, aspect_ratio, width, world, lights, cam)
+    };
+    let first_frame = frames.map_or(0, |f| f.0);
+    let mut cache = SceneCache::default();
+    let (background, aspect_ratio, mut width, world, lights, cam) =
+        load_frame(first_frame, None, &mut cache);
+    let mut height = ((width as f64) / aspect_ratio) as u32;
+    if let Some(w) = args.width {
+        width = w;
+        height = ((width as f64) / aspect_ratio) as u32;
+    }
+    if let Some(h) = args.height {
+        height = h;
+        width = ((height as f64) * aspect_ratio) as u32;
+    }
+    if let Some(prefix) = path.parent() {
+        std::fs::create_dir_all(prefix).expect("Cannot create all the parents");
+    }
+    let heuristic = match args.heuristic.as_str() {
+        "none" => None,
+        "balance" => Some(Heuristic::Balance),
+        "power" => Some(Heuristic::Power),
+        _ => {
+            println!(
+                "{}",
+                style("Unknown heuristic, use none, balance or power.").red()
+            );
+            exit(1);
+        }
+    };
+    let (bidirectional, photons) = match args.integrator.as_str() {
+        "path" => (false, None),
+        "bdpt" => (true, None),
+        "photon" => {
+            if matches!(args.photon_radius, Some(r) if r.is_nan() || r <= 0.0) {
+                println!("{}", style("Photon radius must be positive.").red());
+                exit(1);
+            }
+            let photons = PhotonSettings {
+                count: args.photons,
+                radius: args.photon_radius,
+                progressive: args.progressive,
+                alpha: args.photon_alpha.clamp(0.0, 1.0),
             };
-            let pdf_val = p.value(&scattered.dir);
-            emitted
-                + srec.attenuation
-                    * (rec.mat_ptr.scattering_pdf(r, &rec, &mut scattered))
-                    * (ray_color(&scattered, lights, background, world, depth - 1) / pdf_val)
-        } else {
-            emitted
+            (false, Some(photons))
         }
+        _ => {
+            println!(
+                "{}",
+                style("Unknown integrator, use path, bdpt or photon.").red()
+            );
+            exit(1);
+        }
+    };
+    if args.spectral && (bidirectional || photons.is_some()) {
+        println!(
+            "{}",
+            style("Spectral rendering is only supported by the path integrator.").red()
+        );
+        exit(1);
+    }
+    let integrator = Integrator {
+        max_depth: args.depth,
+        rr_depth: args.rr_depth,
+        heuristic,
+        light_weight: args.light_weight.clamp(0.0, 1.0),
+        bidirectional,
+        spectral: args.spectral,
+    };
+    let filter = match Filter::from_name(&args.filter, args.filter_radius) {
+        Some(f) => f,
+        None => {
+            println!(
+                "{}",
+                style(format!(
+                    "Unknown filter or non-positive radius, use {}.",
+                    FILTER_NAMES.join(", ")
+                ))
+                .red()
+            );
+            exit(1);
+        }
+    };
+    let tile_order = match args.tile_order.as_str() {
+        "scanline" => TileOrder::Scanline,
+        "spiral" => TileOrder::Spiral,
+        _ => {
+            println!(
+                "{}",
+                style("Unknown tile order, use scanline or spiral.").red()
+            );
+            exit(1);
+        }
+    };
+    if make_sampler(&args.sampler, args.spp, args.seed).is_none() {
+        println!(
+            "{}",
+            style(format!(
+                "Unknown sampler, use {}.",
+                SAMPLER_NAMES.join(", ")
+            ))
+            .red()
+        );
+        exit(1);
+    }
+    //Ctrl-C 后不再领取新的块，已渲染的部分照常合并并保存，之后的帧不再渲染
+    let stop = Arc::new(AtomicBool::new(false));
+    let stop_in_handler = stop.clone();
+    //同一进程里再次渲染时（比如测试里）处理函数已经装过，不用再装
+    match ctrlc::set_handler(move || stop_in_handler.store(true, Ordering::Relaxed)) {
+        Ok(()) | Err(ctrlc::Error::MultipleHandlers) => {}
+        Err(e) => panic!("Cannot set the Ctrl-C handler: {}", e),
+    }
+    let settings = Settings {
+        format,
+        tone,
+        requested,
+        aovs,
+        denoiser,
+        integrator,
+        photons,
+        tile_order,
+        filter,
+        width,
+        height,
+        background,
+        stop,
+    };
+    let scene_name = args
+        .scene_file
+        .clone()
+        .unwrap_or_else(|| args.scene.clone());
+    //只有相机动时各帧共用第一帧的 BVH
+    let rebuild = matches!(&scene_file, Some(f) if f.animates_objects());
+    let mut world = Arc::new(world);
+    let mut lights = Arc::new(lights);
+    let mut cam = cam;
+    let frame_list: Vec<Option<u32>> = match frames {
+        Some((start, end)) => (start..=end).map(Some).collect(),
+        None => vec![None],
+    };
+    for frame in frame_list {
+        if settings.stop.load(Ordering::Relaxed) {
+            break;
+        }
+        let (frame_path, checkpoint, spp_map, cost_map, frame_scene_name, resume) = match frame {
+            None => (
+                path.to_path_buf(),
+                args.checkpoint
+                    .clone()
+                    .unwrap_or_else(|| format!("{}.ckpt", args.output)),
+                args.spp_map.clone(),
+                args.cost_map.clone(),
+                scene_name.clone(),
+                args.resume,
+            ),
+            Some(f) => {
+                if f != first_frame {
+                    if rebuild {
+                        //上一帧渲染完后 BVH 只剩这一份引用，可以拿来 refit
+                        let (_, _, _, w, l, c) =
+                            load_frame(f, Arc::try_unwrap(world).ok(), &mut cache);
+                        world = Arc::new(w);
+                        lights = Arc::new(l);
+                        cam = c;
+                    } else if let Some(file) = &scene_file {
+                        cam = file.camera_at(f as f64);
+                    }
+                }
+                println!(
+                    "Frame {} ({}-{})",
+                    f,
+                    first_frame,
+                    frames.map_or(f, |r| r.1)
+                );
+                let frame_path = numbered_path(path, f);
+                let checkpoint = match &args.checkpoint {
+                    Some(c) => numbered_path(Path::new(c), f).display().to_string(),
+                    None => format!("{}.ckpt", frame_path.display()),
+                };
+                //序列里没有 checkpoint 的帧从头渲染
+                let resume = args.resume && Path::new(&checkpoint).exists();
+                (
+                    frame_path,
+                    checkpoint,
+                    args.spp_map
+                        .as_ref()
+                        .map(|m| numbered_path(Path::new(m), f).display().to_string()),
+                    args.cost_map
+                        .as_ref()
+                        .map(|m| numbered_path(Path::new(m), f).display().to_string()),
+                    format!("{}@{}", scene_name, f),
+                    resume,
+                )
+            }
+        };
+        render_frame(
+            &args,
+            &settings,
+            Frame {
+                path: frame_path,
+                checkpoint,
+                spp_map,
+                cost_map,
+                scene_name: frame_scene_name,
+                resume,
+                world: world.clone(),
+                lights: lights.clone(),
+                cam,
+            },
+        );
+    }
+}
+//顶层物体按顺序从 1 开始编号，再建 BVH
+fn build_bvh(world: HittableList) -> BvhNode {
+    let mut objects = number_objects(world);
+    let end = objects.len() as u32;
+    BvhNode::new_nodes(&mut objects, 0, end, 0.0, 1.0)
+}
+fn number_objects(world: HittableList) -> Vec<Box<dyn Hittable>> {
+    world
+        .objects
+        .into_iter()
+        .enumerate()
+        .map(|(k, o)| {
+            Box::new(ObjectId {
+                id: k as u32 + 1,
+                ptr: o,
+            }) as Box<dyn Hittable>
+        })
+        .collect()
+}
+//动画的下一帧：物体个数不变时沿用上一帧 BVH 的拓扑，换上新的物体后 refit，划分变得太差才重建
+fn refit_bvh(mut bvh: BvhNode, world: HittableList) -> BvhNode {
+    if let Err(mut objects) = bvh.set_objects(number_objects(world)) {
+        let end = objects.len() as u32;
+        return BvhNode::new_nodes(&mut objects, 0, end, 0.0, 1.0);
+    }
+    bvh.refit(0.0, 1.0);
+    let (built, refitted) = (bvh.built_cost(), bvh.quality().sah_cost);
+    if bvh.needs_rebuild() {
+        println!(
+            "Refitting raised the BVH SAH cost from {:.2} to {:.2}, rebuilding",
+            built, refitted
+        );
+        let mut objects = bvh.into_objects();
+        let end = objects.len() as u32;
+        BvhNode::new_nodes(&mut objects, 0, end, 0.0, 1.0)
     } else {
-        background
+        println!(
+            "Refitted the BVH, SAH cost {:.2} (built at {:.2})",
+            refitted, built
+        );
+        bvh
     }
 }
-fn main() {
-    let option_edgedetect = false; //true时边缘检测，false时生成图片
-    if !option_edgedetect {
-        let path = std::path::Path::new("output/book3/work.jpg");
-        let prefix = path.parent().unwrap();
-        std::fs::create_dir_all(prefix).expect("Cannot create all the parents");
-        let quality = 100;
-        let samples_per_pixel = 10; //1000
-        let max_depth = 50;
-        let (background, aspect_ratio, width, mut world, cam) = earth();
-        let height = ((width as f64) / aspect_ratio) as u32;
-        let lights = HittableList::new();
-        let img: RgbImage = ImageBuffer::new(width, height);
-        let end = world.objects.len() as u32;
-        let bvh = BvhNode::new_nodes(&mut world.objects, 0, end, 0.0, 1.0);
-        let thread_num = 20; //必须是图像高度的因数
-        let main_progress = Arc::new(Mutex::new(MultiProgress::new()));
-        let bvh_a = Arc::new(bvh);
-        let im = Arc::new(Mutex::new(img));
-        let lights = Arc::new(lights);
+//序列中第 frame 帧的文件名：文件名里有一串 # 时换成同样位数的帧号，否则在扩展名前插入四位帧号
+fn numbered_path(path: &Path, frame: u32) -> PathBuf {
+    let name = path.file_name().and_then(|s| s.to_str()).unwrap_or("out");
+    match name.find('#') {
+        Some(start) => {
+            let digits = name[start..].chars().take_while(|&c| c == '#').count();
+            let number = format!("{:0width$}", frame, width = digits);
+            path.with_file_name(format!(
+                "{}{}{}",
+                &name[..start],
+                number,
+                &name[start + digits..]
+            ))
+        }
+        None => pass_path(path, &format!("{:04}", frame)),
+    }
+}
+fn render_frame(args: &RenderArgs, settings: &Settings, frame: Frame) {
+    let Settings {
+        format,
+        tone,
+        requested,
+        aovs,
+        denoiser,
+        integrator,
+        photons,
+        tile_order,
+        filter,
+        width,
+        height,
+        background,
+        stop,
+    } = settings;
+    let (width, height, background, integrator, filter) =
+        (*width, *height, *background, *integrator, *filter);
+    let path = frame.path.as_path();
+    let checkpoint = &frame.checkpoint;
+    let scene_name = &frame.scene_name;
+    let cam = frame.cam;
+    let mut film = if frame.resume {
+        match FrameBuffer::load_checkpoint(checkpoint) {
+            Ok((mut fb, name))
+                if fb.width == width
+                    && fb.height == height
+                    && &name == scene_name
+                    && fb.film.filter == filter =>
+            {
+                fb.aov = AovBuffer::new(width, height, aovs);
+                fb
+            }
+            Ok((fb, name)) => {
+                println!(
+                    "{}",
+                    style(format!(
+                        "Checkpoint is for scene \"{}\" at {}x{} with the {} filter of radius {}, \
+                         not \"{}\" at {}x{} with the {} filter of radius {}.",
+                        name,
+                        fb.width,
+                        fb.height,
+                        fb.film.filter.name(),
+                        fb.film.filter.radius,
+                        scene_name,
+                        width,
+                        height,
+                        filter.name(),
+                        filter.radius
+                    ))
+                    .red()
+                );
+                exit(1);
+            }
+            Err(e) => {
+                println!("{}", style(e).red());
+                exit(1);
+            }
+        }
+    } else {
+        FrameBuffer::new(width, height, filter, aovs)
+    };
+    let samples_per_pixel = args.spp;
+    let seed = args.seed;
+    let pass_spp = args.pass_spp.max(1);
+    let thread_num = args.threads.max(1);
+    let bvh_a = frame.world;
+    let lights = frame.lights;
+    let remaining: u64 = film
+        .samples
+        .iter()
+        .map(|&n| samples_per_pixel.saturating_sub(n) as u64)
+        .sum();
+    let progress = ProgressBar::new(remaining);
+    let mut last_checkpoint = Instant::now();
+    //渐进时第 i 个 pass 的半径平方为 r²·Π(k+α)/(k+1)，k = 1..i，续渲时从已有的 pass 数接着缩小
+    let photon_radius = photons.map(|ps| {
+        ps.radius.unwrap_or_else(|| {
+            let mut b = AABB {
+                minimum: Point3::new(),
+                maximum: Point3::new(),
+            };
+            bvh_a.bounding_box(cam.sample_time(0.0), cam.sample_time(1.0), &mut b);
+            0.0025 * (b.maximum - b.minimum).length()
+        })
+    });
+    let mut pass = film.samples.iter().copied().max().unwrap_or(0) / pass_spp;
+    //第 k 个渲染线程与第 k 个发射光子的线程在各 pass 里的计数之和
+    let mut render_stats = vec![RayStats::default(); thread_num as usize];
+    let mut photon_stats: Vec<RayStats> = vec![];
+    let start = Instant::now();
+    let mut photon_map: Option<Arc<PhotonMap>> = None;
+    while !stop.load(Ordering::Relaxed) {
+        let todo = film.pass_samples(
+            pass_spp,
+            args.min_spp.min(samples_per_pixel),
+            samples_per_pixel,
+            args.error_target,
+        );
+        if todo.iter().all(|&n| n == 0) {
+            break;
+        }
+        let queue = Arc::new(TileQueue::new(width, height, args.tile_size, *tile_order));
+        let todo = Arc::new(todo);
+        if let (Some(ps), Some(r1)) = (photons, photon_radius) {
+            if photon_map.is_none() || ps.progressive {
+                let (i, r) = if ps.progressive {
+                    let scale: f64 = (1..=pass)
+                        .map(|k| (k as f64 + ps.alpha) / (k as f64 + 1.0))
+                        .product();
+                    (pass, r1 * scale.sqrt())
+                } else {
+                    (0, r1)
+                };
+                let map = trace_photons(
+                    &bvh_a,
+                    &lights,
+                    &cam,
+                    ps.count,
+                    integrator.max_depth,
+                    r,
+                    seed,
+                    i,
+                    thread_num,
+                );
+                if photon_stats.len() < map.stats.len() {
+                    photon_stats.resize(map.stats.len(), RayStats::default());
+                }
+                for (total, s) in photon_stats.iter_mut().zip(&map.stats) {
+                    *total += *s;
+                }
+                photon_map = Some(Arc::new(map));
+            }
+        }
+        pass += 1;
+        let done_samples = Arc::new(film.samples.clone());
         let mut handles = vec![];
-        for p in 0..thread_num {
-            let progress = Arc::new(
-                (*main_progress.lock().unwrap())
-                    .add(ProgressBar::new((height * width / thread_num) as u64)),
-            );
-            //let b_in_thread = b.clone();
+        for _ in 0..thread_num.min(queue.len() as u32) {
             let bvh_a_in_thread = bvh_a.clone();
-            let im_in_thread = im.clone();
             let lights_in_thread = lights.clone();
+            let photons_in_thread = photon_map.clone();
+            let queue_in_thread = queue.clone();
+            let todo_in_thread = todo.clone();
+            let done_in_thread = done_samples.clone();
+            let stop_in_thread = stop.clone();
+            let progress_in_thread = progress.clone();
+            let aovs_in_thread = aovs.clone();
+            let sampler_name = args.sampler.clone();
             let each_thread = thread::spawn(move || {
-                for j in (0..height).rev() {
-                    if j % thread_num == p {
-                        for i in 0..width {
-                            let mut pixel_color: Color = Color { e: (0.0, 0.0, 0.0) };
-                            let mut s = 0;
-                            while s < samples_per_pixel {
-                                let u = (1.0 * (i as f64) + random_double(0.0, 1.0))
-                                    / (width - 1) as f64;
-                                let v = (1.0 * ((height - j - 1) as f64) + random_double(0.0, 1.0))
-                                    / (height - 1) as f64;
-                                let r: Ray = cam.get_ray(u, v);
-                                pixel_color.add_assign(ray_color(
-                                    &r,
-                                    lights_in_thread.as_ref(),
-                                    background,
-                                    bvh_a_in_thread.as_ref(),
-                                    max_depth,
-                                ));
-                                s += 1;
+                let mut sampler = make_sampler(&sampler_name, samples_per_pixel, seed).unwrap();
+                let view = CameraView {
+                    cam: &cam,
+                    width,
+                    height,
+                };
+                let mut done = vec![];
+                while let Some(tile) = queue_in_thread.next_tile() {
+                    if stop_in_thread.load(Ordering::Relaxed) {
+                        break;
+                    }
+                    let mut buffer = TileBuffer::new(tile, &aovs_in_thread, filter, width, height);
+                    let mut tile_samples = 0;
+                    for j in tile.y0..tile.y0 + tile.height {
+                        for i in tile.x0..tile.x0 + tile.width {
+                            let pixel = (j * width + i) as usize;
+                            let n = todo_in_thread[pixel];
+                            for s in 0..n {
+                                let sample = done_in_thread[pixel] + s;
+                                let before = stats::snapshot();
+                                let mut rng =
+                                    Rng::new(sample_seed(seed, pixel as u64, sample as u64));
+                                sampler.start_pixel_sample(pixel as u64, sample);
+                                let (du, dv) = sampler.get_2d();
+                                let u = (1.0 * (i as f64) + du) / (width - 1) as f64;
+                                let v =
+                                    (1.0 * ((height - j - 1) as f64) + dv) / (height - 1) as f64;
+                                let r: Ray = cam.get_ray(u, v, sampler.as_mut());
+                                let sample = if integrator.bidirectional {
+                                    integrator.bdpt(
+                                        &r,
+                                        &view,
+                                        lights_in_thread.as_ref(),
+                                        background,
+                                        bvh_a_in_thread.as_ref(),
+                                        sampler.as_mut(),
+                                        &mut rng,
+                                        &mut buffer.splats,
+                                    )
+                                } else {
+                                    let wavelengths = integrator
+                                        .spectral
+                                        .then(|| SampledWavelengths::sample(sampler.get_1d()));
+                                    integrator.ray_color(
+                                        &r,
+                                        lights_in_thread.as_ref(),
+                                        background,
+                                        bvh_a_in_thread.as_ref(),
+                                        sampler.as_mut(),
+                                        &mut rng,
+                                        photons_in_thread.as_deref(),
+                                        wavelengths.as_ref(),
+                                    )
+                                };
+                                //图像坐标 y 向下，与 v 相反
+                                buffer.add(i, j, i as f64 + du, (j + 1) as f64 - dv, &sample);
+                                buffer.add_cost(
+                                    i,
+                                    j,
+                                    (stats::snapshot() - before).traversal_cost(),
+                                );
                             }
-                            let mut img1 = im_in_thread.lock().unwrap();
-                            let pixel = (*img1).get_pixel_mut(i, j);
-                            *pixel = write_color(pixel_color, samples_per_pixel);
-                            progress.inc(1);
+                            tile_samples += n as u64;
                         }
                     }
+                    progress_in_thread.inc(tile_samples);
+                    done.push(buffer);
                 }
-                progress.finish();
+                (done, stats::take())
             });
             handles.push(each_thread);
         }
-
-        main_progress.lock().unwrap().join().unwrap();
-
-        for th in handles {
-            th.join().unwrap();
+        //块之间的滤波区域有重叠，按块的位置顺序合并，使结果与线程数无关
+        let mut buffers: Vec<TileBuffer> = vec![];
+        for (k, th) in handles.into_iter().enumerate() {
+            let (done, s) = th.join().unwrap();
+            buffers.extend(done);
+            render_stats[k] += s;
+        }
+        buffers.sort_by_key(|b| (b.tile.y0, b.tile.x0));
+        for buffer in &buffers {
+            film.add_tile(buffer);
         }
+        //每个 pass 结束写一次预览，隔一段时间存一次 checkpoint
+        save_image(&film, path, format, tone);
+        if args.checkpoint_interval > 0
+            && last_checkpoint.elapsed().as_secs() >= args.checkpoint_interval
+        {
+            save_checkpoint(&film, checkpoint, scene_name);
+            last_checkpoint = Instant::now();
+        }
+    }
+    progress.finish();
+    let per_thread: Vec<(String, RayStats)> = render_stats
+        .into_iter()
+        .enumerate()
+        .map(|(k, s)| (format!("render {}", k), s))
+        .chain(
+            photon_stats
+                .into_iter()
+                .enumerate()
+                .map(|(k, s)| (format!("photon {}", k), s)),
+        )
+        .collect();
+    stats::report(&per_thread, start.elapsed().as_secs_f64(), args.stats);
+    match denoiser {
+        Some(d) => save_denoised(&film, d, path, format, tone),
+        None => save_image(&film, path, format, tone),
+    }
+    save_aovs(&film, requested, path, format, tone);
+    if let Some(map) = &frame.spp_map {
+        let heat = image::DynamicImage::ImageRgb8(film.spp_image(samples_per_pixel));
+        if heat.save(map).is_err() {
+            println!("{}", style("Outputting spp map fails.").red());
+        }
+    }
+    if let Some(map) = &frame.cost_map {
+        let (img, max) = film.cost_image();
+        if image::DynamicImage::ImageRgb8(img).save(map).is_err() {
+            println!("{}", style("Outputting cost map fails.").red());
+        } else {
+            println!(
+                "Cost map scale: 0 to {:.1} BVH nodes and primitives per sample",
+                max
+            );
+        }
+    }
+    //只有要求了 checkpoint 或者渲染被打断时才在最后写一次
+    let interrupted = stop.load(Ordering::Relaxed);
+    if args.checkpoint.is_some() || args.checkpoint_interval > 0 || interrupted {
+        save_checkpoint(&film, checkpoint, scene_name);
+    }
+    if interrupted {
+        println!(
+            "{}",
+            style(format!(
+                "Interrupted at {} spp, resume with --resume.",
+                film.min_samples()
+            ))
+            .yellow()
+        );
+    }
+    if args.error_target.is_some() {
+        let total: u64 = film.samples.iter().map(|&n| n as u64).sum();
         println!(
-            "Ouput image as \"{}\"",
-            style(path.to_str().unwrap()).yellow()
+            "Average spp: {:.1}",
+            total as f64 / film.samples.len().max(1) as f64
         );
-        let image_now = (*(im.lock().unwrap())).clone();
-        let output_image = image::DynamicImage::ImageRgb8(image_now);
-        let mut output_file = File::create(path).unwrap();
-        match output_image.write_to(&mut output_file, image::ImageOutputFormat::Jpeg(quality)) {
-            Ok(_) => {}
-            Err(_) => println!("{}", style("Outputting image fails.").red()),
+    }
+    println!(
+        "Ouput image as \"{}\"",
+        style(path.to_str().unwrap()).yellow()
+    );
+}
+fn save_image(film: &FrameBuffer, path: &Path, format: &OutputFormat, tone: &ToneMapper) {
+    if let Err(e) = write_image(&film.resolve(), path, format, tone) {
+        println!("{}", style(e).red());
+    }
+}
+//AOV 等附加的图写到与结果图同目录，文件名为 <名字>.<pass>.<扩展名>
+fn pass_path(path: &Path, pass: &str) -> PathBuf {
+    let stem = path.file_stem().and_then(|s| s.to_str()).unwrap_or("out");
+    let mut file = format!("{}.{}", stem, pass);
+    if let Some(ext) = path.extension().and_then(|e| e.to_str()) {
+        file = format!("{}.{}", file, ext);
+    }
+    path.with_file_name(file)
+}
+fn save_aovs(
+    film: &FrameBuffer,
+    requested: &[Aov],
+    path: &Path,
+    format: &OutputFormat,
+    tone: &ToneMapper,
+) {
+    for (k, aov) in film.aov.aovs.iter().enumerate() {
+        if !requested.contains(aov) {
+            continue;
+        }
+        let aov_path = pass_path(path, aov.name());
+        let img = film.aov.resolve(k);
+        let result = match format {
+            OutputFormat::Ldr(_) if !aov.is_radiance() => {
+                write_image(&aov.display(&img), &aov_path, format, &ToneMapper::raw())
+            }
+            _ => write_image(&img, &aov_path, format, tone),
+        };
+        if let Err(e) = result {
+            println!("{}", style(e).red());
+        }
+    }
+}
+//降噪后的图写到 path，原图写到 <名字>.noisy.<扩展名>
+fn save_denoised(
+    film: &FrameBuffer,
+    denoiser: &Denoiser,
+    path: &Path,
+    format: &OutputFormat,
+    tone: &ToneMapper,
+) {
+    let noisy = film.resolve();
+    let albedo = film.aov.resolve_aov(Aov::Albedo).unwrap();
+    let normal = film.aov.resolve_aov(Aov::ShadingNormal).unwrap();
+    let depth = film.aov.resolve_aov(Aov::Depth).unwrap();
+    let variance = film.variance();
+    let guides = Guides {
+        albedo: &albedo,
+        normal: &normal,
+        depth: &depth,
+        variance: &variance,
+    };
+    let denoised = denoiser.denoise(&noisy, &guides);
+    for (img, p) in [
+        (&noisy, pass_path(path, "noisy")),
+        (&denoised, path.to_path_buf()),
+    ] {
+        if let Err(e) = write_image(img, &p, format, tone) {
+            println!("{}", style(e).red());
+        }
+    }
+}
+fn save_checkpoint(film: &FrameBuffer, checkpoint: &str, scene_name: &str) {
+    if let Err(e) = film.save_checkpoint(checkpoint, scene_name) {
+        println!("{}", style(e).red());
+    }
+}
+fn list_scenes() {
+    println!("Built-in scenes:");
+    for name in BUILTIN_SCENES.iter() {
+        println!("  {}", name);
+    }
+    println!("Scene files in raytracer/scenes:");
+    if let Ok(entries) = std::fs::read_dir("raytracer/scenes") {
+        let mut files: Vec<String> = entries
+            .filter_map(|e| e.ok())
+            .map(|e| e.path().display().to_string())
+            .collect();
+        files.sort();
+        for f in files {
+            println!("  {}", f);
+        }
+    }
+}
+//逐个模型读入三角形、建树，打印用时与树的质量；读不出来的模型报错后跳过
+fn bvh_report(args: BvhArgs) {
+    let mut models = args.models;
+    if models.is_empty() {
+        if let Ok(entries) = std::fs::read_dir("raytracer/objects") {
+            models = entries
+                .filter_map(|e| e.ok())
+                .filter_map(|e| e.file_name().into_string().ok())
+                .filter(|name| Path::new(&format!("raytracer/objects/{0}/{0}.obj", name)).exists())
+                .collect();
+            models.sort();
+        }
+    }
+    for name in models {
+        if !Path::new(&format!("raytracer/objects/{0}/{0}.obj", name)).exists() {
+            println!(
+                "{}",
+                style(format!("No model raytracer/objects/{0}/{0}.obj.", name)).red()
+            );
+            continue;
         }
-        exit(0);
+        let start = Instant::now();
+        let mut triangles = match obj_triangles(&name) {
+            Ok(triangles) => triangles,
+            Err(e) => {
+                println!("{}: {}", style(&name).yellow(), style(e).red());
+                continue;
+            }
+        };
+        let load_time = start.elapsed().as_secs_f64();
+        let start = Instant::now();
+        let end = triangles.len() as u32;
+        let bvh = BvhNode::new_nodes(&mut triangles, 0, end, 0.0, 0.0);
+        let build_time = start.elapsed().as_secs_f64();
+        let q = bvh.quality();
+        println!(
+            "{}: {} triangles, loaded in {:.3}s, built in {:.3}s",
+            style(&name).yellow(),
+            q.primitives,
+            load_time,
+            build_time
+        );
+        println!(
+            "  SAH cost {:.2}, {} nodes, {} leaves, depth {}, leaf size {:.2} mean / {} max",
+            q.sah_cost,
+            q.nodes,
+            q.leaves,
+            q.max_depth,
+            q.primitives as f64 / q.leaves.max(1) as f64,
+            q.max_leaf_size
+        );
+    }
+}
+fn main() {
+    let cli = Cli::parse();
+    //不带子命令时和原来一样渲染默认场景
+    let command = cli
+        .command
+        .unwrap_or_else(|| Command::Render(RenderArgs::parse_from(["render"])));
+    match command {
+        Command::Render(args) => render(args),
+        Command::Edgedetect(args) => edgedetect(&args.input, &args.output, args.threads),
+        Command::ListScenes => list_scenes(),
+        Command::Bvh(args) => bvh_report(args),
     }
-    edgedetect("book3/workfinal.jpg", "book3/test_ran_scene_e2.jpg", 20);
     exit(0);
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    //同一个种子，不管多少线程、块怎么分给线程，结果都一样
+    #[test]
+    fn same_seed_same_image_for_any_thread_count() {
+        let render_with = |threads: u32| -> Vec<u8> {
+            let out = std::env::temp_dir().join(format!(
+                "rt-seed-{}-{}.pfm",
+                std::process::id(),
+                threads
+            ));
+            let threads = threads.to_string();
+            let out_str = out.to_str().unwrap();
+            render(RenderArgs::parse_from([
+                "render",
+                "-s",
+                "cornell_box_smoke",
+                "--width",
+                "24",
+                "--spp",
+                "6",
+                "--pass-spp",
+                "4",
+                "--tile-size",
+                "5",
+                "--seed",
+                "7",
+                "-j",
+                &threads,
+                "--checkpoint-interval",
+                "0",
+                "-o",
+                out_str,
+            ]));
+            let data = std::fs::read(&out).unwrap();
+            std::fs::remove_file(&out).unwrap();
+            data
+        };
+        assert_eq!(render_with(1), render_with(4));
+    }
+}
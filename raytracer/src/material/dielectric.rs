@@ -1,12 +1,29 @@
 use crate::material::Material;
 use crate::material::ScatterRecord;
-use crate::randoms::{min, random_double};
+use crate::randoms::min;
+use crate::spectrum::{hero_wavelength, Dispersion, SODIUM_D};
 use crate::vec3::Color;
 use crate::vec3::{mul_vec_dot, reflect, refract};
 use crate::{hittable::HitRecord, ray::Ray};
+//dispersion 为 None 时折射率恒为 ir；否则光谱渲染时按 hero 波长计算，RGB 渲染时用钠 D 线处的值
 #[derive(Clone)]
 pub struct Dielectric {
     pub ir: f64,
+    pub dispersion: Option<Dispersion>,
+}
+impl Dielectric {
+    pub const fn new(ir: f64) -> Self {
+        Self {
+            ir,
+            dispersion: None,
+        }
+    }
+    pub fn dispersive(dispersion: Dispersion) -> Self {
+        Self {
+            ir: dispersion.ior(SODIUM_D),
+            dispersion: Some(dispersion),
+        }
+    }
 }
 
 pub fn reflectance(cosine: f64, ref_idx: f64) -> f64 {
@@ -14,19 +31,17 @@ pub fn reflectance(cosine: f64, ref_idx: f64) -> f64 {
     r0 * r0 + (1.0 - r0 * r0) * ((1.0 - cosine).powf(5.0))
 }
 impl Material for Dielectric {
-    fn scatter(&self, r_in: &Ray, rec: &HitRecord) -> Option<ScatterRecord> {
-        let reflection_ratio = if rec.front_face {
-            1.0 / self.ir
-        } else {
-            self.ir
+    fn scatter(&self, r_in: &Ray, rec: &HitRecord, u: f64) -> Option<ScatterRecord> {
+        let (ir, dispersive) = match (self.dispersion, hero_wavelength()) {
+            (Some(d), Some(lambda)) => (d.ior(lambda), true),
+            _ => (self.ir, false),
         };
+        let reflection_ratio = if rec.front_face { 1.0 / ir } else { ir };
         let unit_direction = r_in.dir.unit_vector();
         let cos_theta = min(mul_vec_dot(-unit_direction, rec.normal), 1.0);
         let sin_theta = (1.0 - cos_theta * cos_theta).sqrt();
         let cannot_refract = reflection_ratio * sin_theta > 1.0;
-        let direction = if cannot_refract
-            || reflectance(cos_theta, reflection_ratio) > random_double(0.0, 1.0)
-        {
+        let direction = if cannot_refract || reflectance(cos_theta, reflection_ratio) > u {
             reflect(unit_direction, rec.normal)
         } else {
             refract(unit_direction, rec.normal, reflection_ratio)
@@ -40,6 +55,10 @@ impl Material for Dielectric {
             is_specular: true,
             attenuation: Color { e: (1.0, 1.0, 1.0) },
             pdf_ptr: None,
+            dispersive,
         })
     }
+    fn albedo(&self, _rec: &HitRecord) -> Color {
+        Color { e: (1.0, 1.0, 1.0) }
+    }
 }
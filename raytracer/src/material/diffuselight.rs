@@ -15,7 +15,7 @@ impl DiffuseLight<SolidColor> {
     }
 }
 impl<T: Texture> Material for DiffuseLight<T> {
-    fn scatter(&self, _r_in: &Ray, _rec: &HitRecord) -> Option<ScatterRecord> {
+    fn scatter(&self, _r_in: &Ray, _rec: &HitRecord, _u: f64) -> Option<ScatterRecord> {
         None
     }
     fn emitted(&self, _r_in: &Ray, rec: &HitRecord, u: f64, v: f64, p: &Point3) -> Color {
@@ -1,8 +1,10 @@
+use std::f64::consts::PI;
+
 use crate::material::Material;
 use crate::material::ScatterRecord;
-use crate::randoms::random_in_unit_sphere;
+use crate::pdf::SpherePdf;
 use crate::texture::{solodcolor::SolidColor, Texture};
-use crate::vec3::Color;
+use crate::vec3::{Color, Vec3};
 use crate::{hittable::HitRecord, ray::Ray};
 
 #[derive(Clone)]
@@ -17,16 +19,23 @@ impl Isotropic<SolidColor> {
     }
 }
 impl<T: Texture> Material for Isotropic<T> {
-    fn scatter(&self, r_in: &Ray, rec: &HitRecord) -> Option<ScatterRecord> {
+    fn scatter(&self, _r_in: &Ray, rec: &HitRecord, _u: f64) -> Option<ScatterRecord> {
         Some(ScatterRecord {
             specular_ray: Ray {
-                orig: rec.p,
-                dir: random_in_unit_sphere(),
-                time: r_in.time,
+                orig: Vec3::new(),
+                dir: Vec3::new(),
+                time: 0.0,
             },
-            is_specular: true,
+            is_specular: false,
             attenuation: self.albedo.value(rec.u, rec.v, &rec.p),
-            pdf_ptr: None,
+            pdf_ptr: Some(Box::new(SpherePdf)),
+            dispersive: false,
         })
     }
+    fn scattering_pdf(&self, _r_in: &Ray, _rec: &HitRecord, _scattered: &mut Ray) -> f64 {
+        1.0 / (4.0 * PI)
+    }
+    fn albedo(&self, rec: &HitRecord) -> Color {
+        self.albedo.value(rec.u, rec.v, &rec.p)
+    }
 }
@@ -4,14 +4,14 @@ use crate::material::Material;
 use crate::material::ScatterRecord;
 use crate::pdf::CosinePdf;
 use crate::texture::Texture;
-use crate::vec3::{mul_vec_dot, Onb, Vec3};
+use crate::vec3::{mul_vec_dot, Color, Onb, Vec3};
 use crate::{hittable::HitRecord, ray::Ray};
 #[derive(Clone)]
 pub struct Lambertian<T: Texture> {
     pub albedo: T,
 }
 impl<T: Texture> Material for Lambertian<T> {
-    fn scatter(&self, _r_in: &Ray, rec: &HitRecord) -> Option<ScatterRecord> {
+    fn scatter(&self, _r_in: &Ray, rec: &HitRecord, _u: f64) -> Option<ScatterRecord> {
         Some(ScatterRecord {
             specular_ray: Ray {
                 orig: Vec3::new(),
@@ -23,6 +23,7 @@ impl<T: Texture> Material for Lambertian<T> {
             pdf_ptr: Some(Box::new(CosinePdf {
                 uvw: Onb::build_from_w(&rec.normal),
             })),
+            dispersive: false,
         })
     }
     fn scattering_pdf(&self, _r_in: &Ray, rec: &HitRecord, scattered: &mut Ray) -> f64 {
@@ -33,4 +34,7 @@ impl<T: Texture> Material for Lambertian<T> {
             cosine / PI
         }
     }
+    fn albedo(&self, rec: &HitRecord) -> Color {
+        self.albedo.value(rec.u, rec.v, &rec.p)
+    }
 }
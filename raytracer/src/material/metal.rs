@@ -1,9 +1,9 @@
 use crate::material::Material;
 use crate::material::ScatterRecord;
-use crate::randoms::random_in_unit_sphere;
+use crate::pdf::{FuzzPdf, Pdf};
 use crate::vec3::reflect;
 use crate::vec3::Color;
-use crate::vec3::Vec3;
+use crate::vec3::{mul_vec_dot, Vec3};
 use crate::{hittable::HitRecord, ray::Ray};
 #[derive(Clone)]
 pub struct Metal {
@@ -11,18 +11,48 @@ pub struct Metal {
     pub fuzz: f64,
 }
 impl Material for Metal {
-    fn scatter(&self, r_in: &Ray, rec: &HitRecord) -> Option<ScatterRecord> {
+    fn scatter(&self, r_in: &Ray, rec: &HitRecord, _u: f64) -> Option<ScatterRecord> {
         let reflected: Vec3 = reflect(r_in.dir.unit_vector(), rec.normal);
-
+        //完全光滑时只有一个方向，仍按镜面处理
+        if self.fuzz <= 0.0 {
+            return Some(ScatterRecord {
+                specular_ray: Ray {
+                    orig: (rec.p),
+                    dir: reflected,
+                    time: r_in.time,
+                },
+                is_specular: true,
+                attenuation: self.albedo,
+                pdf_ptr: None,
+                dispersive: false,
+            });
+        }
         Some(ScatterRecord {
             specular_ray: Ray {
-                orig: (rec.p),
-                dir: (reflected + random_in_unit_sphere() * self.fuzz),
-                time: r_in.time,
+                orig: Vec3::new(),
+                dir: Vec3::new(),
+                time: 0.0,
             },
-            is_specular: true,
+            is_specular: false,
             attenuation: self.albedo,
-            pdf_ptr: None,
+            pdf_ptr: Some(Box::new(FuzzPdf {
+                reflected,
+                fuzz: self.fuzz,
+            })),
+            dispersive: false,
         })
     }
+    fn scattering_pdf(&self, r_in: &Ray, rec: &HitRecord, scattered: &mut Ray) -> f64 {
+        if mul_vec_dot(scattered.dir, rec.normal) <= 0.0 {
+            return 0.0;
+        }
+        FuzzPdf {
+            reflected: reflect(r_in.dir.unit_vector(), rec.normal),
+            fuzz: self.fuzz,
+        }
+        .value(&scattered.dir)
+    }
+    fn albedo(&self, _rec: &HitRecord) -> Color {
+        self.albedo
+    }
 }
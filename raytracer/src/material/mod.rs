@@ -3,6 +3,8 @@ pub mod diffuselight;
 pub mod isotropic;
 pub mod lambertian;
 pub mod metal;
+use std::sync::Arc;
+
 use crate::pdf::Pdf;
 use crate::vec3::{Color, Point3};
 use crate::{hittable::HitRecord, ray::Ray};
@@ -12,9 +14,12 @@ pub struct ScatterRecord {
     pub is_specular: bool,
     pub attenuation: Color,
     pub pdf_ptr: Option<Box<dyn Pdf>>,
+    //光谱渲染时散射方向取决于 hero 波长，其余波长要终止
+    pub dispersive: bool,
 }
 pub trait Material {
-    fn scatter(&self, _r_in: &Ray, _rec: &HitRecord) -> Option<ScatterRecord> {
+    //u 为 [0,1) 上的一维采样，用来在多个分量（如反射与折射）之间选择
+    fn scatter(&self, _r_in: &Ray, _rec: &HitRecord, _u: f64) -> Option<ScatterRecord> {
         None
     }
     fn scattering_pdf(&self, _r_in: &Ray, _rec: &HitRecord, _scattered: &mut Ray) -> f64 {
@@ -23,4 +28,53 @@ pub trait Material {
     fn emitted(&self, _r_in: &Ray, _rec: &HitRecord, _u: f64, _v: f64, _p: &Point3) -> Color {
         Color { e: (0.0, 0.0, 0.0) }
     }
+    //AOV 用：击中处的反照率
+    fn albedo(&self, _rec: &HitRecord) -> Color {
+        Color { e: (0.0, 0.0, 0.0) }
+    }
+    //AOV 用：材质编号，0 表示未编号
+    fn id(&self) -> u32 {
+        0
+    }
+}
+//从场景文件载入的材质是动态类型，需要共享
+impl<M: Material + ?Sized> Material for Arc<M> {
+    fn scatter(&self, r_in: &Ray, rec: &HitRecord, u: f64) -> Option<ScatterRecord> {
+        (**self).scatter(r_in, rec, u)
+    }
+    fn scattering_pdf(&self, r_in: &Ray, rec: &HitRecord, scattered: &mut Ray) -> f64 {
+        (**self).scattering_pdf(r_in, rec, scattered)
+    }
+    fn emitted(&self, r_in: &Ray, rec: &HitRecord, u: f64, v: f64, p: &Point3) -> Color {
+        (**self).emitted(r_in, rec, u, v, p)
+    }
+    fn albedo(&self, rec: &HitRecord) -> Color {
+        (**self).albedo(rec)
+    }
+    fn id(&self) -> u32 {
+        (**self).id()
+    }
+}
+//给材质加上编号
+#[derive(Clone)]
+pub struct WithId<M: Material> {
+    pub id: u32,
+    pub mat: M,
+}
+impl<M: Material> Material for WithId<M> {
+    fn scatter(&self, r_in: &Ray, rec: &HitRecord, u: f64) -> Option<ScatterRecord> {
+        self.mat.scatter(r_in, rec, u)
+    }
+    fn scattering_pdf(&self, r_in: &Ray, rec: &HitRecord, scattered: &mut Ray) -> f64 {
+        self.mat.scattering_pdf(r_in, rec, scattered)
+    }
+    fn emitted(&self, r_in: &Ray, rec: &HitRecord, u: f64, v: f64, p: &Point3) -> Color {
+        self.mat.emitted(r_in, rec, u, v, p)
+    }
+    fn albedo(&self, rec: &HitRecord) -> Color {
+        self.mat.albedo(rec)
+    }
+    fn id(&self) -> u32 {
+        self.id
+    }
 }
@@ -0,0 +1,159 @@
+use std::{
+    fs::File,
+    io::{BufWriter, Write},
+    path::Path,
+};
+
+use image::{codecs::hdr::HdrEncoder, ImageOutputFormat, Rgb, RgbImage};
+
+use crate::{
+    framebuffer::HdrImage,
+    tonemap::{srgb_encode, ToneMapper},
+};
+
+pub enum OutputFormat {
+    //8 位图像，写出前做色调映射与 sRGB 编码
+    Ldr(ImageOutputFormat),
+    //以下均直接写线性的 f32 辐亮度
+    Exr,
+    Hdr,
+    Pfm,
+}
+
+impl OutputFormat {
+    //format 为 None 时按扩展名决定
+    pub fn from_path(path: &Path, format: &Option<String>, quality: u8) -> Option<Self> {
+        let name = match format {
+            Some(f) => f.to_lowercase(),
+            None => path.extension()?.to_str()?.to_lowercase(),
+        };
+        match name.as_str() {
+            "jpg" | "jpeg" => Some(Self::Ldr(ImageOutputFormat::Jpeg(quality))),
+            "png" => Some(Self::Ldr(ImageOutputFormat::Png)),
+            "ppm" => Some(Self::Ldr(ImageOutputFormat::Pnm(
+                image::pnm::PNMSubtype::Pixmap(image::pnm::SampleEncoding::Binary),
+            ))),
+            "bmp" => Some(Self::Ldr(ImageOutputFormat::Bmp)),
+            "exr" => Some(Self::Exr),
+            "hdr" => Some(Self::Hdr),
+            "pfm" => Some(Self::Pfm),
+            _ => None,
+        }
+    }
+}
+
+pub fn write_image(
+    img: &HdrImage,
+    path: &Path,
+    format: &OutputFormat,
+    tone: &ToneMapper,
+) -> Result<(), String> {
+    let err = |e: std::io::Error| format!("cannot write {}: {}", path.display(), e);
+    let mut w = BufWriter::new(File::create(path).map_err(err)?);
+    match format {
+        OutputFormat::Ldr(f) => {
+            let output_image = image::DynamicImage::ImageRgb8(to_rgb8(img, tone));
+            output_image
+                .write_to(&mut w, f.clone())
+                .map_err(|e| format!("cannot write {}: {}", path.display(), e))?;
+        }
+        OutputFormat::Exr => write_exr(img, &mut w).map_err(err)?,
+        OutputFormat::Hdr => {
+            let data: Vec<Rgb<f32>> = img.data.iter().map(|p| Rgb(*p)).collect();
+            HdrEncoder::new(&mut w)
+                .encode(&data, img.width as usize, img.height as usize)
+                .map_err(|e| format!("cannot write {}: {}", path.display(), e))?;
+        }
+        OutputFormat::Pfm => write_pfm(img, &mut w).map_err(err)?,
+    }
+    w.flush().map_err(err)
+}
+
+pub fn to_rgb8(img: &HdrImage, tone: &ToneMapper) -> RgbImage {
+    RgbImage::from_fn(img.width, img.height, |i, j| {
+        let p = tone.map(img.data[(j * img.width + i) as usize]);
+        let q = |x: f32| {
+            let x = if tone.srgb { srgb_encode(x) } else { x };
+            (255.0 * x + 0.5) as u8
+        };
+        Rgb([q(p[0]), q(p[1]), q(p[2])])
+    })
+}
+
+//PFM：文本头之后是由下到上的行，每个像素三个小端 f32
+fn write_pfm<W: Write>(img: &HdrImage, w: &mut W) -> std::io::Result<()> {
+    write!(w, "PF\n{} {}\n-1.0\n", img.width, img.height)?;
+    for j in (0..img.height).rev() {
+        for i in 0..img.width {
+            for x in img.data[(j * img.width + i) as usize] {
+                w.write_all(&x.to_le_bytes())?;
+            }
+        }
+    }
+    Ok(())
+}
+
+//单部分、按扫描线存储、不压缩的 OpenEXR，通道为 32 位浮点的 B、G、R（按字母序）
+fn write_exr<W: Write>(img: &HdrImage, w: &mut W) -> std::io::Result<()> {
+    fn attribute<W: Write>(w: &mut W, name: &str, kind: &str, value: &[u8]) -> std::io::Result<()> {
+        w.write_all(name.as_bytes())?;
+        w.write_all(&[0])?;
+        w.write_all(kind.as_bytes())?;
+        w.write_all(&[0])?;
+        w.write_all(&(value.len() as i32).to_le_bytes())?;
+        w.write_all(value)
+    }
+    let (width, height) = (img.width as i32, img.height as i32);
+    let mut header = vec![0x76, 0x2f, 0x31, 0x01, 2, 0, 0, 0];
+    let mut channels = vec![];
+    for name in ["B", "G", "R"] {
+        channels.extend_from_slice(name.as_bytes());
+        channels.push(0);
+        channels.extend_from_slice(&2i32.to_le_bytes()); //FLOAT
+        channels.extend_from_slice(&[0, 0, 0, 0]); //pLinear 与保留字节
+        channels.extend_from_slice(&1i32.to_le_bytes());
+        channels.extend_from_slice(&1i32.to_le_bytes());
+    }
+    channels.push(0);
+    attribute(&mut header, "channels", "chlist", &channels)?;
+    attribute(&mut header, "compression", "compression", &[0])?;
+    let mut window = vec![];
+    for x in [0, 0, width - 1, height - 1] {
+        window.extend_from_slice(&x.to_le_bytes());
+    }
+    attribute(&mut header, "dataWindow", "box2i", &window)?;
+    attribute(&mut header, "displayWindow", "box2i", &window)?;
+    attribute(&mut header, "lineOrder", "lineOrder", &[0])?;
+    attribute(
+        &mut header,
+        "pixelAspectRatio",
+        "float",
+        &1.0f32.to_le_bytes(),
+    )?;
+    attribute(&mut header, "screenWindowCenter", "v2f", &[0; 8])?;
+    attribute(
+        &mut header,
+        "screenWindowWidth",
+        "float",
+        &1.0f32.to_le_bytes(),
+    )?;
+    header.push(0);
+    w.write_all(&header)?;
+
+    //每行一个块：行号、数据长度、然后各通道依次一整行
+    let line_len = 8 + 12 * img.width as usize;
+    let table_end = header.len() + 8 * img.height as usize;
+    for j in 0..img.height as usize {
+        w.write_all(&((table_end + j * line_len) as u64).to_le_bytes())?;
+    }
+    for j in 0..img.height {
+        w.write_all(&(j as i32).to_le_bytes())?;
+        w.write_all(&((12 * img.width) as i32).to_le_bytes())?;
+        for c in [2, 1, 0] {
+            for i in 0..img.width {
+                w.write_all(&img.data[(j * img.width + i) as usize][c].to_le_bytes())?;
+            }
+        }
+    }
+    Ok(())
+}
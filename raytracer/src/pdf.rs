@@ -2,13 +2,14 @@ use std::f64::consts::PI;
 
 use crate::{
     hittable::Hittable,
-    randoms::{random_cosine_direction, random_double, random_in_semi_sphere},
+    randoms::{sample_cosine_direction, sample_hemisphere, sample_sphere},
+    sampler::Sampler,
     vec3::{mul_vec_dot, Onb, Point3, Vec3},
 };
 
 pub trait Pdf {
     fn value(&self, direction: &Vec3) -> f64;
-    fn generate(&self) -> Vec3;
+    fn generate(&self, sampler: &mut dyn Sampler) -> Vec3;
 }
 #[derive(Clone)]
 pub struct TestPdf {
@@ -23,9 +24,8 @@ impl Pdf for TestPdf {
             1.0 / (2.0 * PI)
         }
     }
-    fn generate(&self) -> Vec3 {
-        self.uvw
-            .local_vec(&random_in_semi_sphere(Vec3 { e: (0.0, 0.0, 1.0) }))
+    fn generate(&self, sampler: &mut dyn Sampler) -> Vec3 {
+        self.uvw.local_vec(&sample_hemisphere(sampler.get_2d()))
     }
 }
 #[derive(Clone)]
@@ -41,8 +41,20 @@ impl Pdf for CosinePdf {
             cosine / PI
         }
     }
-    fn generate(&self) -> Vec3 {
-        self.uvw.local_vec(&random_cosine_direction())
+    fn generate(&self, sampler: &mut dyn Sampler) -> Vec3 {
+        self.uvw
+            .local_vec(&sample_cosine_direction(sampler.get_2d()))
+    }
+}
+//各向同性的相函数：整个单位球面上均匀
+#[derive(Clone)]
+pub struct SpherePdf;
+impl Pdf for SpherePdf {
+    fn value(&self, _direction: &Vec3) -> f64 {
+        1.0 / (4.0 * PI)
+    }
+    fn generate(&self, sampler: &mut dyn Sampler) -> Vec3 {
+        sample_sphere(sampler.get_2d())
     }
 }
 #[derive(Clone)]
@@ -54,24 +66,56 @@ impl<'a, H: Hittable> Pdf for HittablePdf<'a, H> {
     fn value(&self, direction: &Vec3) -> f64 {
         self.ptr.pdf_value(&self.o, direction)
     }
-    fn generate(&self) -> Vec3 {
-        self.ptr.random(&self.o)
+    fn generate(&self, sampler: &mut dyn Sampler) -> Vec3 {
+        self.ptr.random(&self.o, sampler)
     }
 }
 
+//weight 是按 p1 采样的概率
 pub struct MixturePdf<'a, P1: Pdf + ?Sized, P2: Pdf + ?Sized> {
     pub p1: &'a P1,
     pub p2: &'a P2,
+    pub weight: f64,
 }
 impl<'a, P1: Pdf + ?Sized, P2: Pdf + ?Sized> Pdf for MixturePdf<'a, P1, P2> {
     fn value(&self, direction: &Vec3) -> f64 {
-        0.5 * self.p1.value(direction) + 0.5 * self.p2.value(direction)
+        self.weight * self.p1.value(direction) + (1.0 - self.weight) * self.p2.value(direction)
     }
-    fn generate(&self) -> Vec3 {
-        if random_double(0.0, 1.0) < 0.5 {
-            self.p1.generate()
+    fn generate(&self, sampler: &mut dyn Sampler) -> Vec3 {
+        if sampler.get_1d() < self.weight {
+            self.p1.generate(sampler)
         } else {
-            self.p2.generate()
+            self.p2.generate(sampler)
+        }
+    }
+}
+//Metal 的模糊反射：reflected 加上半径为 fuzz 的球内均匀随机向量
+#[derive(Clone)]
+pub struct FuzzPdf {
+    pub reflected: Vec3,
+    pub fuzz: f64,
+}
+impl Pdf for FuzzPdf {
+    fn value(&self, direction: &Vec3) -> f64 {
+        //沿 direction 的射线穿过模糊球的那一段，对体积积分 t^2 dt
+        let d = direction.unit_vector();
+        let b = mul_vec_dot(d, self.reflected);
+        let discriminant = b * b - self.reflected.length_square() + self.fuzz * self.fuzz;
+        if discriminant <= 0.0 {
+            return 0.0;
+        }
+        let sqrtd = discriminant.sqrt();
+        let t1 = (b - sqrtd).max(0.0);
+        let t2 = b + sqrtd;
+        if t2 <= t1 {
+            return 0.0;
         }
+        let volume = 4.0 / 3.0 * PI * self.fuzz * self.fuzz * self.fuzz;
+        (t2 * t2 * t2 - t1 * t1 * t1) / (3.0 * volume)
+    }
+    fn generate(&self, sampler: &mut dyn Sampler) -> Vec3 {
+        //球内均匀：半径按体积取立方根
+        let r = sampler.get_1d().cbrt();
+        self.reflected + sample_sphere(sampler.get_2d()) * (r * self.fuzz)
     }
 }
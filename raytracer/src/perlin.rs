@@ -1,5 +1,5 @@
 use crate::{
-    randoms::{random_int, random_vec},
+    randoms::Rng,
     vec3::{mul_num, mul_vec_dot, Point3, Vec3},
 };
 #[derive(Clone)]
@@ -9,17 +9,17 @@ pub struct Perlin {
     pub perm_y: [i32; 256],
     pub perm_z: [i32; 256],
 }
-pub fn permute(p: &mut [i32; 256], n: i32) {
+pub fn permute(p: &mut [i32; 256], n: i32, rng: &mut Rng) {
     for i in (1..n).rev() {
-        let target = random_int(0, i);
+        let target = rng.random_int(0, i);
         p.swap(i as usize, target as usize)
     }
 }
-pub fn perlin_generate_perm(p: &mut [i32; 256]) {
+pub fn perlin_generate_perm(p: &mut [i32; 256], rng: &mut Rng) {
     for i in 0..256 {
         p[i as usize] = i;
     }
-    permute(p, 256);
+    permute(p, 256, rng);
 }
 pub fn trilinear_interp(c: &[f64; 8], u: f64, v: f64, w: f64) -> f64 {
     let mut accum = 0.0;
@@ -54,7 +54,7 @@ pub fn perlin_interp(c: &[Vec3; 8], u: f64, v: f64, w: f64) -> f64 {
     accum
 }
 impl Perlin {
-    pub fn new() -> Self {
+    pub fn new(rng: &mut Rng) -> Self {
         let mut ans = Perlin {
             ranvec: [Vec3::new(); 256],
             perm_x: [0; 256],
@@ -62,11 +62,11 @@ impl Perlin {
             perm_z: [0; 256],
         };
         for i in 0..256 {
-            ans.ranvec[i] = random_vec(-1.0, 1.0).unit_vector();
+            ans.ranvec[i] = rng.random_vec(-1.0, 1.0).unit_vector();
         }
-        perlin_generate_perm(&mut ans.perm_x);
-        perlin_generate_perm(&mut ans.perm_y);
-        perlin_generate_perm(&mut ans.perm_z);
+        perlin_generate_perm(&mut ans.perm_x, rng);
+        perlin_generate_perm(&mut ans.perm_y, rng);
+        perlin_generate_perm(&mut ans.perm_z, rng);
         ans
     }
     pub fn noise(&self, p: &Point3) -> f64 {
@@ -100,8 +100,3 @@ impl Perlin {
         accum.abs()
     }
 }
-impl Default for Perlin {
-    fn default() -> Self {
-        Self::new()
-    }
-}
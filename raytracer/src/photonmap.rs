@@ -0,0 +1,236 @@
+use std::{f64::consts::PI, f64::INFINITY, sync::Arc, thread};
+
+use crate::{
+    bvh::BvhNode,
+    camera::Camera,
+    hittable::{HitRecord, Hittable, HittableList},
+    integrator::sample_emission,
+    randoms::{sample_seed, Rng},
+    ray::Ray,
+    sampler::make_sampler,
+    stats::{self, RayStats},
+    vec3::{mul_vec_dot, Color, Point3, Vec3},
+};
+
+//与相机采样的随机数错开
+const PHOTON_SEED: u64 = 0x5048_4f54_4f4e;
+
+//落在漫反射表面上的一个光子，wi 为指向来处的单位向量，power 未除以发射的光子数
+#[derive(Clone, Copy)]
+pub struct Photon {
+    pub p: Point3,
+    pub wi: Vec3,
+    pub power: Color,
+}
+
+//焦散光子图：只存从光源出发、经过至少一次镜面（含折射与介质）后落在非镜面表面上的光子，
+//按 kd 树排好，以 radius 为半径做密度估计
+pub struct PhotonMap {
+    //隐式 kd 树：区间的中点为节点，左右两半为子树
+    photons: Vec<Photon>,
+    axes: Vec<u8>,
+    emitted: u32,
+    pub radius: f64,
+    //发射这些光子时各线程的光线计数
+    pub stats: Vec<RayStats>,
+}
+
+fn coord(p: &Point3, axis: u8) -> f64 {
+    match axis {
+        0 => p.e.0,
+        1 => p.e.1,
+        _ => p.e.2,
+    }
+}
+
+//沿包围盒最长的轴取中位数划分
+fn build(photons: &mut [Photon], axes: &mut [u8]) {
+    if photons.len() <= 1 {
+        return;
+    }
+    let mut lo = [INFINITY; 3];
+    let mut hi = [-INFINITY; 3];
+    for ph in photons.iter() {
+        for a in 0..3 {
+            lo[a] = lo[a].min(coord(&ph.p, a as u8));
+            hi[a] = hi[a].max(coord(&ph.p, a as u8));
+        }
+    }
+    let axis = (0..3u8)
+        .max_by(|&a, &b| {
+            (hi[a as usize] - lo[a as usize])
+                .partial_cmp(&(hi[b as usize] - lo[b as usize]))
+                .unwrap()
+        })
+        .unwrap();
+    let mid = photons.len() / 2;
+    photons.select_nth_unstable_by(mid, |a, b| {
+        coord(&a.p, axis).partial_cmp(&coord(&b.p, axis)).unwrap()
+    });
+    axes[mid] = axis;
+    let (left, right) = photons.split_at_mut(mid);
+    let (axes_left, axes_right) = axes.split_at_mut(mid);
+    build(left, axes_left);
+    build(&mut right[1..], &mut axes_right[1..]);
+}
+
+fn visit(photons: &[Photon], axes: &[u8], p: &Point3, r2: f64, f: &mut impl FnMut(&Photon)) {
+    if photons.is_empty() {
+        return;
+    }
+    let mid = photons.len() / 2;
+    let node = &photons[mid];
+    if (node.p - *p).length_square() <= r2 {
+        f(node);
+    }
+    let d = coord(p, axes[mid]) - coord(&node.p, axes[mid]);
+    let (near, far) = if d <= 0.0 {
+        ((0, mid), (mid + 1, photons.len()))
+    } else {
+        ((mid + 1, photons.len()), (0, mid))
+    };
+    visit(&photons[near.0..near.1], &axes[near.0..near.1], p, r2, f);
+    if d * d <= r2 {
+        visit(&photons[far.0..far.1], &axes[far.0..far.1], p, r2, f);
+    }
+}
+
+impl PhotonMap {
+    pub fn new(photons: Vec<Photon>, emitted: u32, radius: f64) -> Self {
+        let mut photons: Vec<Photon> = photons
+            .into_iter()
+            .filter(|ph| ph.p.e.0.is_finite() && ph.p.e.1.is_finite() && ph.p.e.2.is_finite())
+            .collect();
+        let mut axes = vec![0; photons.len()];
+        build(&mut photons, &mut axes);
+        Self {
+            photons,
+            axes,
+            emitted,
+            radius,
+            stats: vec![],
+        }
+    }
+    pub fn len(&self) -> usize {
+        self.photons.len()
+    }
+    pub fn is_empty(&self) -> bool {
+        self.photons.is_empty()
+    }
+    //到 p 的距离不超过 radius 的光子
+    pub fn for_each_near(&self, p: &Point3, radius: f64, mut f: impl FnMut(&Photon)) {
+        visit(&self.photons, &self.axes, p, radius * radius, &mut f);
+    }
+    //r_in 击中 rec 处、朝 -r_in 方向出射的焦散辐亮度：半径内的光子按 f 加权后除以圆盘面积
+    pub fn estimate(&self, r_in: &Ray, rec: &HitRecord, attenuation: &Color) -> Color {
+        if self.emitted == 0 {
+            return Color::new();
+        }
+        let mut sum = Color::new();
+        self.for_each_near(&rec.p, self.radius, |ph| {
+            let cos = mul_vec_dot(ph.wi, rec.normal);
+            if cos <= 0.0 {
+                return;
+            }
+            let mut scattered = Ray {
+                orig: rec.p,
+                dir: ph.wi,
+                time: r_in.time,
+            };
+            let f = *attenuation * rec.mat_ptr.scattering_pdf(r_in, rec, &mut scattered) / cos;
+            sum += f * ph.power;
+        });
+        sum / (PI * self.radius * self.radius * self.emitted as f64)
+    }
+}
+
+//第 pass 轮的光子：从 lights 发射 count 个，分给 threads 个线程；
+//每个光子按 (seed, pass, 序号) 设种子，结果与线程数无关
+#[allow(clippy::too_many_arguments)]
+pub fn trace_photons(
+    world: &Arc<BvhNode>,
+    lights: &Arc<HittableList>,
+    cam: &Camera,
+    count: u32,
+    max_depth: i32,
+    radius: f64,
+    seed: u64,
+    pass: u32,
+    threads: u32,
+) -> PhotonMap {
+    let threads = threads.max(1).min(count.max(1));
+    let chunk = (count + threads - 1) / threads;
+    let handles: Vec<_> = (0..threads)
+        .map(|k| {
+            let world = world.clone();
+            let lights = lights.clone();
+            let cam = *cam;
+            thread::spawn(move || {
+                let total_area = lights.area();
+                let mut sampler = make_sampler("independent", 1, seed ^ PHOTON_SEED).unwrap();
+                let mut stored = vec![];
+                for index in k * chunk..((k + 1) * chunk).min(count) {
+                    let mut rng =
+                        Rng::new(sample_seed(seed ^ PHOTON_SEED, index as u64, pass as u64));
+                    sampler.start_pixel_sample(index as u64, pass);
+                    let time = cam.sample_time(sampler.get_1d());
+                    let e = match sample_emission(
+                        lights.as_ref(),
+                        world.as_ref(),
+                        total_area,
+                        time,
+                        sampler.as_mut(),
+                        &mut rng,
+                    ) {
+                        Some(e) => e,
+                        None => continue,
+                    };
+                    //余弦发光：Le·cos / (1/A · cos/π)
+                    let mut power = e.le * (PI * total_area);
+                    let mut ray = Ray {
+                        orig: e.p,
+                        dir: e.dir,
+                        time,
+                    };
+                    let mut specular = false;
+                    for _ in 0..max_depth.max(0) {
+                        stats::secondary_ray();
+                        let rec = match world.hit(&ray, 0.001, INFINITY, &mut rng) {
+                            Some(rec) => rec,
+                            None => break,
+                        };
+                        let srec = match rec.mat_ptr.scatter(&ray, &rec, sampler.get_1d()) {
+                            Some(srec) => srec,
+                            None => break,
+                        };
+                        if !srec.is_specular {
+                            //直接照明与漫反射间接光由路径追踪负责
+                            if specular {
+                                stored.push(Photon {
+                                    p: rec.p,
+                                    wi: -ray.dir.unit_vector(),
+                                    power,
+                                });
+                            }
+                            break;
+                        }
+                        specular = true;
+                        power = power * srec.attenuation;
+                        ray = srec.specular_ray;
+                    }
+                }
+                (stored, stats::take())
+            })
+        })
+        .collect();
+    let mut photons = vec![];
+    let mut thread_stats = vec![];
+    for h in handles {
+        let (stored, s) = h.join().unwrap();
+        photons.extend(stored);
+        thread_stats.push(s);
+    }
+    let mut map = PhotonMap::new(photons, count, radius);
+    map.stats = thread_stats;
+    map
+}
@@ -1,10 +1,48 @@
 //随机相关
-use crate::vec3::{mul_vec_dot, Vec3};
-use rand::Rng;
+use crate::vec3::Vec3;
 use std::f64::consts::PI;
-pub fn random_double(min: f64, max: f64) -> f64 {
-    let mut rng = rand::thread_rng();
-    min + rng.gen_range(0.0..(max - min))
+//SplitMix64，状态只有一个 u64，方便按像素与采样序号重新设种子
+#[derive(Clone, Copy)]
+pub struct Rng {
+    state: u64,
+}
+impl Rng {
+    pub fn new(seed: u64) -> Self {
+        Self { state: seed }
+    }
+    pub fn next_u64(&mut self) -> u64 {
+        self.state = self.state.wrapping_add(0x9e37_79b9_7f4a_7c15);
+        mix(self.state)
+    }
+    //[0,1)
+    pub fn next_f64(&mut self) -> f64 {
+        (self.next_u64() >> 11) as f64 * (1.0 / (1u64 << 53) as f64)
+    }
+    pub fn random_double(&mut self, min: f64, max: f64) -> f64 {
+        min + (max - min) * self.next_f64()
+    }
+    pub fn random_vec(&mut self, min: f64, max: f64) -> Vec3 {
+        Vec3 {
+            e: (
+                self.random_double(min, max),
+                self.random_double(min, max),
+                self.random_double(min, max),
+            ),
+        }
+    }
+    //[min,max] 上的整数
+    pub fn random_int(&mut self, min: i32, max: i32) -> i32 {
+        self.random_double(min as f64, (max + 1) as f64) as i32
+    }
+}
+pub fn mix(mut z: u64) -> u64 {
+    z = (z ^ (z >> 30)).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+    z = (z ^ (z >> 27)).wrapping_mul(0x94d0_49bb_1331_11eb);
+    z ^ (z >> 31)
+}
+//由全局种子、像素序号与该像素的采样序号得到一个采样的种子，与线程数无关
+pub fn sample_seed(seed: u64, pixel: u64, sample: u64) -> u64 {
+    mix(mix(mix(seed).wrapping_add(pixel)).wrapping_add(sample))
 }
 pub fn clamp(x: f64, min: f64, max: f64) -> f64 {
     if x < min {
@@ -29,68 +67,46 @@ pub fn max(x: f64, y: f64) -> f64 {
         y
     }
 }
-pub fn random_vec(min: f64, max: f64) -> Vec3 {
+//以下把 [0,1)^2 上的采样点 u 映射成各种分布
+//半球上均匀分布，z 轴朝上
+pub fn sample_hemisphere(u: (f64, f64)) -> Vec3 {
+    let z = u.0;
+    let r = (1.0 - z * z).max(0.0).sqrt();
+    let phi = 2.0 * PI * u.1;
     Vec3 {
-        e: (
-            random_double(min, max),
-            random_double(min, max),
-            random_double(min, max),
-        ),
+        e: (r * phi.cos(), r * phi.sin(), z),
     }
 }
-pub fn random_in_unit_sphere() -> Vec3 {
-    let mut p = random_vec(-1.0, 1.0);
-    loop {
-        if p.length_square() < 1.0 {
-            break;
-        }
-        p = random_vec(-1.0, 1.0);
-    }
-    p
-}
-pub fn random_unit_vec() -> Vec3 {
-    random_in_unit_sphere().unit_vector()
-}
-pub fn random_in_semi_sphere(normal: Vec3) -> Vec3 {
-    let in_unit_sphere = random_in_unit_sphere();
-    if mul_vec_dot(in_unit_sphere, normal) > 0.0 {
-        in_unit_sphere
-    } else {
-        -in_unit_sphere
+//单位球面上均匀分布
+pub fn sample_sphere(u: (f64, f64)) -> Vec3 {
+    let z = 1.0 - 2.0 * u.0;
+    let r = (1.0 - z * z).max(0.0).sqrt();
+    let phi = 2.0 * PI * u.1;
+    Vec3 {
+        e: (r * phi.cos(), r * phi.sin(), z),
     }
 }
-pub fn random_in_unit_disk() -> Vec3 {
-    let mut p = Vec3 {
-        e: (random_double(-1.0, 1.0), random_double(-1.0, 1.0), 0.0),
-    };
-    loop {
-        if p.length_square() < 1.0 {
-            break;
-        }
-        p = Vec3 {
-            e: (random_double(-1.0, 1.0), random_double(-1.0, 1.0), 0.0),
-        };
+//单位圆盘内均匀分布
+pub fn sample_disk(u: (f64, f64)) -> Vec3 {
+    let r = u.0.sqrt();
+    let phi = 2.0 * PI * u.1;
+    Vec3 {
+        e: (r * phi.cos(), r * phi.sin(), 0.0),
     }
-    p
 }
-pub fn random_cosine_direction() -> Vec3 {
-    let r1 = random_double(0.0, 1.0);
-    let r2 = random_double(0.0, 1.0);
+pub fn sample_cosine_direction(u: (f64, f64)) -> Vec3 {
+    let (r1, r2) = u;
     let z = (1.0 - r2).sqrt();
     let phi = 2.0 * PI * r1;
     let x = phi.cos() * r2.sqrt();
     let y = phi.sin() * r2.sqrt();
     Vec3 { e: (x, y, z) }
 }
-pub fn random_to_sphere(radius: f64, distance_squared: f64) -> Vec3 {
-    let r1 = random_double(0.0, 1.0);
-    let r2 = random_double(0.0, 1.0);
+pub fn sample_to_sphere(radius: f64, distance_squared: f64, u: (f64, f64)) -> Vec3 {
+    let (r1, r2) = u;
     let z = 1.0 + r2 * ((1.0 - radius * radius / distance_squared).sqrt() - 1.0);
     let phi = 2.0 * PI * r1;
     let x = phi.cos() * (1.0 - z * z).sqrt();
     let y = phi.sin() * (1.0 - z * z).sqrt();
     Vec3 { e: (x, y, z) }
 }
-pub fn random_int(min: i32, max: i32) -> i32 {
-    (random_double(min as f64, (max + 1) as f64)) as i32
-}
@@ -0,0 +1,351 @@
+use crate::randoms::{mix, sample_seed, Rng};
+
+//给相机、材质、光源提供 [0,1) 上的采样点，每个像素的每个采样按维度依次取
+pub trait Sampler {
+    //开始 pixel 的第 index 个采样，维度从 0 重新数起
+    fn start_pixel_sample(&mut self, pixel: u64, index: u32);
+    fn get_1d(&mut self) -> f64;
+    fn get_2d(&mut self) -> (f64, f64);
+}
+
+pub const SAMPLER_NAMES: [&str; 4] = ["independent", "stratified", "halton", "sobol"];
+
+//spp 用于分层，seed 为全局种子
+pub fn make_sampler(name: &str, spp: u32, seed: u64) -> Option<Box<dyn Sampler + Send>> {
+    let state = SamplerState::new(seed);
+    Some(match name {
+        "independent" => Box::new(IndependentSampler { state }),
+        "stratified" => Box::new(StratifiedSampler::new(state, spp)),
+        "halton" => Box::new(HaltonSampler { state }),
+        "sobol" => Box::new(SobolSampler { state }),
+        _ => return None,
+    })
+}
+
+//各采样器共用的状态：当前像素、采样序号、维度，以及本采样的随机数
+struct SamplerState {
+    seed: u64,
+    pixel: u64,
+    index: u32,
+    dim: u32,
+    rng: Rng,
+}
+
+impl SamplerState {
+    fn new(seed: u64) -> Self {
+        Self {
+            seed,
+            pixel: 0,
+            index: 0,
+            dim: 0,
+            rng: Rng::new(seed),
+        }
+    }
+    fn start(&mut self, pixel: u64, index: u32) {
+        self.pixel = pixel;
+        self.index = index;
+        self.dim = 0;
+        //与 randoms 里的种子错开
+        self.rng = Rng::new(sample_seed(!self.seed, pixel, index as u64));
+    }
+    //取下一个维度的编号
+    fn next_dim(&mut self, n: u32) -> u32 {
+        let d = self.dim;
+        self.dim += n;
+        d
+    }
+    //只与像素和维度有关的哈希，同一像素的各个采样共用
+    fn dim_hash(&self, dim: u32) -> u32 {
+        (sample_seed(self.seed, self.pixel, 0x1_0000_0000 + dim as u64) >> 32) as u32
+    }
+}
+
+pub struct IndependentSampler {
+    state: SamplerState,
+}
+
+impl Sampler for IndependentSampler {
+    fn start_pixel_sample(&mut self, pixel: u64, index: u32) {
+        self.state.start(pixel, index);
+    }
+    fn get_1d(&mut self) -> f64 {
+        self.state.rng.next_f64()
+    }
+    fn get_2d(&mut self) -> (f64, f64) {
+        (self.state.rng.next_f64(), self.state.rng.next_f64())
+    }
+}
+
+//每一维把 [0,1) 分成 spp 份，每个像素按随机排列让第 index 个采样落在其中一份并在份内抖动；
+//二维分成 nx*ny=spp 的网格
+pub struct StratifiedSampler {
+    state: SamplerState,
+    spp: u32,
+    nx: u32,
+    ny: u32,
+}
+
+impl StratifiedSampler {
+    fn new(state: SamplerState, spp: u32) -> Self {
+        let spp = spp.max(1);
+        let mut nx = (spp as f64).sqrt() as u32;
+        while spp % nx != 0 {
+            nx -= 1;
+        }
+        Self {
+            state,
+            spp,
+            nx,
+            ny: spp / nx,
+        }
+    }
+}
+
+impl Sampler for StratifiedSampler {
+    fn start_pixel_sample(&mut self, pixel: u64, index: u32) {
+        self.state.start(pixel, index);
+    }
+    fn get_1d(&mut self) -> f64 {
+        let dim = self.state.next_dim(1);
+        let stratum = permute(
+            self.state.index % self.spp,
+            self.spp,
+            self.state.dim_hash(dim),
+        );
+        (stratum as f64 + self.state.rng.next_f64()) / self.spp as f64
+    }
+    fn get_2d(&mut self) -> (f64, f64) {
+        let dim = self.state.next_dim(2);
+        let stratum = permute(
+            self.state.index % self.spp,
+            self.spp,
+            self.state.dim_hash(dim),
+        );
+        let (sx, sy) = (stratum % self.nx, stratum / self.nx);
+        (
+            (sx as f64 + self.state.rng.next_f64()) / self.nx as f64,
+            (sy as f64 + self.state.rng.next_f64()) / self.ny as f64,
+        )
+    }
+}
+
+//第 d 维用第 d 个素数为底的根式反演，每个像素每一维对各位数字做 Owen 置乱；
+//只加随机偏移的话，高维（底数大）时少量采样会挤在一小段里
+pub struct HaltonSampler {
+    state: SamplerState,
+}
+
+const PRIMES: [u32; 64] = [
+    2, 3, 5, 7, 11, 13, 17, 19, 23, 29, 31, 37, 41, 43, 47, 53, 59, 61, 67, 71, 73, 79, 83, 89, 97,
+    101, 103, 107, 109, 113, 127, 131, 137, 139, 149, 151, 157, 163, 167, 173, 179, 181, 191, 193,
+    197, 199, 211, 223, 227, 229, 233, 239, 241, 251, 257, 263, 269, 271, 277, 281, 283, 293, 307,
+    311,
+];
+
+//Owen 置乱的根式反演：每一位数字按已确定的低位数字选一个随机排列。
+//i 的数字用完后，之后每一位都是对 0 的独立随机排列，即均匀随机数字，整段用一个哈希出的小数代替；
+//循环只走 i 的有效位数，base^k 不超过 base*2^32，不会溢出 u64
+fn scrambled_radical_inverse(dim: usize, mut i: u32, hash: u32) -> f64 {
+    let base = PRIMES[dim];
+    //底数为 2 时反演就是按位倒序，嵌套均匀置乱可以用位运算一次做完
+    if base == 2 {
+        return nested_uniform_scramble(i.reverse_bits(), hash) as f64 / 4294967296.0;
+    }
+    let mut reversed: u64 = 0;
+    let mut base_k: u64 = 1;
+    //高位的零数位也要置乱，否则小序号的点会挤在同一格里；一直做到 f64 的精度
+    while base_k < 1 << 53 {
+        let digit = i % base;
+        i /= base;
+        let digit_hash = mix(hash as u64 ^ reversed) as u32;
+        reversed = reversed * base as u64 + permute(digit, base, digit_hash) as u64;
+        base_k *= base as u64;
+    }
+    (reversed as f64 / base_k as f64).min(1.0 - f64::EPSILON)
+}
+
+impl HaltonSampler {
+    fn sample(&mut self, dim: u32) -> f64 {
+        //维度用完后退回独立随机数
+        if dim as usize >= PRIMES.len() {
+            return self.state.rng.next_f64();
+        }
+        scrambled_radical_inverse(dim as usize, self.state.index, self.state.dim_hash(dim))
+    }
+}
+
+impl Sampler for HaltonSampler {
+    fn start_pixel_sample(&mut self, pixel: u64, index: u32) {
+        self.state.start(pixel, index);
+    }
+    fn get_1d(&mut self) -> f64 {
+        let dim = self.state.next_dim(1);
+        self.sample(dim)
+    }
+    fn get_2d(&mut self) -> (f64, f64) {
+        let dim = self.state.next_dim(2);
+        (self.sample(dim), self.sample(dim + 1))
+    }
+}
+
+//Owen 置乱的 Sobol 序列（Burley 2020）：每次取二维都用 Sobol 的前两维，
+//按像素与维度打乱采样序号，再对结果做嵌套均匀置乱
+pub struct SobolSampler {
+    state: SamplerState,
+}
+
+fn sobol_2d(i: u32) -> (u32, u32) {
+    let x = i.reverse_bits();
+    let mut y = 0;
+    let mut v = 1u32 << 31;
+    let mut i = i;
+    while i != 0 {
+        if i & 1 != 0 {
+            y ^= v;
+        }
+        i >>= 1;
+        v ^= v >> 1;
+    }
+    (x, y)
+}
+
+fn laine_karras_permutation(mut x: u32, seed: u32) -> u32 {
+    x = x.wrapping_add(seed);
+    x ^= x.wrapping_mul(0x6c50_b47c);
+    x ^= x.wrapping_mul(0xb82f_1e52);
+    x ^= x.wrapping_mul(0xc7af_e638);
+    x ^= x.wrapping_mul(0x8d22_f6e6);
+    x
+}
+
+fn nested_uniform_scramble(x: u32, seed: u32) -> u32 {
+    laine_karras_permutation(x.reverse_bits(), seed).reverse_bits()
+}
+
+impl SobolSampler {
+    fn sample(&mut self, dim: u32) -> (f64, f64) {
+        let h = self.state.dim_hash(dim);
+        let i = nested_uniform_scramble(self.state.index, h);
+        let (x, y) = sobol_2d(i);
+        let x = nested_uniform_scramble(x, h.wrapping_mul(0x9e37_79b9) ^ 0x68bc_21eb);
+        let y = nested_uniform_scramble(y, h.wrapping_mul(0x85eb_ca6b) ^ 0x02e5_be93);
+        (x as f64 / 4294967296.0, y as f64 / 4294967296.0)
+    }
+}
+
+impl Sampler for SobolSampler {
+    fn start_pixel_sample(&mut self, pixel: u64, index: u32) {
+        self.state.start(pixel, index);
+    }
+    fn get_1d(&mut self) -> f64 {
+        let dim = self.state.next_dim(1);
+        self.sample(dim).0
+    }
+    fn get_2d(&mut self) -> (f64, f64) {
+        let dim = self.state.next_dim(2);
+        self.sample(dim)
+    }
+}
+
+//Kensler 的无存储随机排列：把 i 映射到 [0,l) 的一个排列上，p 决定排列
+fn permute(mut i: u32, l: u32, p: u32) -> u32 {
+    if l <= 1 {
+        return 0;
+    }
+    let mut w = l - 1;
+    w |= w >> 1;
+    w |= w >> 2;
+    w |= w >> 4;
+    w |= w >> 8;
+    w |= w >> 16;
+    loop {
+        i ^= p;
+        i = i.wrapping_mul(0xe170_893d);
+        i ^= p >> 16;
+        i ^= (i & w) >> 4;
+        i ^= p >> 8;
+        i = i.wrapping_mul(0x0929_eb3f);
+        i ^= p >> 23;
+        i ^= (i & w) >> 1;
+        i = i.wrapping_mul(1 | p >> 27);
+        i = i.wrapping_mul(0x6935_fa69);
+        i ^= (i & w) >> 11;
+        i = i.wrapping_mul(0x74dc_b303);
+        i ^= (i & w) >> 2;
+        i = i.wrapping_mul(0x9e50_1cc3);
+        i ^= (i & w) >> 2;
+        i = i.wrapping_mul(0xc860_a3df);
+        i &= w;
+        i ^= i >> 5;
+        if i < l {
+            break;
+        }
+    }
+    (i.wrapping_add(p)) % l
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn samples_lie_in_unit_interval() {
+        for name in SAMPLER_NAMES.iter() {
+            let mut sampler = make_sampler(name, 16, 3).unwrap();
+            //大的采样序号与超过素数表的维度也要落在 [0,1)
+            for &index in [0, 1, 15, 255, 65_537, u32::MAX].iter() {
+                for pixel in 0..4 {
+                    sampler.start_pixel_sample(pixel, index);
+                    for _ in 0..40 {
+                        let x = sampler.get_1d();
+                        let (y, z) = sampler.get_2d();
+                        for v in [x, y, z] {
+                            assert!((0.0..1.0).contains(&v), "{} gave {}", name, v);
+                        }
+                    }
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn sobol_matches_reference() {
+        //不置乱时前两维的前 8 个点
+        let x = [0.0, 0.5, 0.25, 0.75, 0.125, 0.625, 0.375, 0.875];
+        let y = [0.0, 0.5, 0.75, 0.25, 0.625, 0.125, 0.375, 0.875];
+        for i in 0..8 {
+            let (a, b) = sobol_2d(i);
+            assert_eq!(a as f64 / 4294967296.0, x[i as usize]);
+            assert_eq!(b as f64 / 4294967296.0, y[i as usize]);
+        }
+    }
+
+    #[test]
+    fn scrambled_halton_keeps_strata() {
+        //Owen 置乱不破坏分层：前 base^k 个点在 [0,1) 的 base^k 等分里各占一格
+        for (dim, &base) in PRIMES.iter().enumerate().take(4) {
+            let n = base.pow(if base == 2 { 6 } else { 3 });
+            for &hash in [0, 1, 0xdead_beef].iter() {
+                let mut seen = vec![false; n as usize];
+                for i in 0..n {
+                    let v = scrambled_radical_inverse(dim, i, hash);
+                    let cell = (v * n as f64) as usize;
+                    assert!(!seen[cell], "base {} index {} hash {}", base, i, hash);
+                    seen[cell] = true;
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn permute_is_a_permutation() {
+        for &l in [1, 2, 3, 7, 16, 100].iter() {
+            let mut seen = vec![false; l as usize];
+            for i in 0..l {
+                let j = permute(i, l, 0x1234_5678) as usize;
+                assert!(!seen[j]);
+                seen[j] = true;
+            }
+        }
+    }
+}
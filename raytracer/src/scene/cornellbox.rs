@@ -5,37 +5,54 @@ use crate::{
         mybox::MyBox, rect::XyRect, rect::XzRect, rect::YzRect, rotate::RotateY,
         translate::Translate,
     },
-    material::{dielectric::Dielectric, diffuselight::DiffuseLight, lambertian::Lambertian},
+    material::{
+        dielectric::Dielectric, diffuselight::DiffuseLight, lambertian::Lambertian, WithId,
+    },
     texture::solodcolor::SolidColor,
     vec3::{Color, Point3, Vec3},
 };
-pub fn cornell_box() -> (Color, f64, u32, HittableList, Camera) {
+pub fn cornell_box() -> (Color, f64, u32, HittableList, HittableList, Camera) {
     let mut objects = HittableList::new();
-    let red = Lambertian {
-        albedo: SolidColor {
-            color_value: Color {
-                e: (0.65, 0.05, 0.05),
+    let red = WithId {
+        id: 1,
+        mat: Lambertian {
+            albedo: SolidColor {
+                color_value: Color {
+                    e: (0.65, 0.05, 0.05),
+                },
             },
         },
     };
-    let white = Lambertian {
-        albedo: SolidColor {
-            color_value: Color {
-                e: (0.73, 0.73, 0.73),
+    let white = WithId {
+        id: 2,
+        mat: Lambertian {
+            albedo: SolidColor {
+                color_value: Color {
+                    e: (0.73, 0.73, 0.73),
+                },
             },
         },
     };
-    let green = Lambertian {
-        albedo: SolidColor {
-            color_value: Color {
-                e: (0.12, 0.45, 0.15),
+    let green = WithId {
+        id: 3,
+        mat: Lambertian {
+            albedo: SolidColor {
+                color_value: Color {
+                    e: (0.12, 0.45, 0.15),
+                },
             },
         },
     };
-    let light = DiffuseLight::new(Color {
-        e: (15.0, 15.0, 15.0),
-    });
-    let glass = Dielectric { ir: 1.5 };
+    let light = WithId {
+        id: 4,
+        mat: DiffuseLight::new(Color {
+            e: (15.0, 15.0, 15.0),
+        }),
+    };
+    let glass = WithId {
+        id: 5,
+        mat: Dielectric::new(1.5),
+    };
     objects.add(Box::new(YzRect {
         y0: 0.0,
         y1: 555.0,
@@ -60,9 +77,18 @@ pub fn cornell_box() -> (Color, f64, u32, HittableList, Camera) {
             z0: 227.0,
             z1: 332.0,
             k: 554.0,
-            mp: light,
+            mp: light.clone(),
         },
     }));
+    let mut lights = HittableList::new();
+    lights.add(Box::new(XzRect {
+        x0: 213.0,
+        x1: 343.0,
+        z0: 227.0,
+        z1: 332.0,
+        k: 554.0,
+        mp: light,
+    }));
     objects.add(Box::new(XzRect {
         x0: 0.0,
         x1: 555.0,
@@ -143,6 +169,7 @@ pub fn cornell_box() -> (Color, f64, u32, HittableList, Camera) {
         1.0,
         600,
         objects,
+        lights,
         Camera::new_cam(
             lookfrom,
             lookat,
@@ -2,17 +2,20 @@ use crate::{
     camera::{Camera, NewCamMessage},
     hittable::sphere::Sphere,
     hittable::HittableList,
-    material::lambertian::Lambertian,
+    material::{lambertian::Lambertian, WithId},
     texture::imagetexture::ImageTexture,
     vec3::{Color, Point3, Vec3},
 };
-pub fn earth() -> (Color, f64, u32, HittableList, Camera) {
+pub fn earth() -> (Color, f64, u32, HittableList, HittableList, Camera) {
     let mut objects = HittableList::new();
     let path = std::path::Path::new("raytracer/src/sourcepictures/earthmap.jpg");
     let earth_texture = ImageTexture::new(path);
 
-    let earth_surface = Lambertian {
-        albedo: earth_texture,
+    let earth_surface = WithId {
+        id: 1,
+        mat: Lambertian {
+            albedo: earth_texture,
+        },
     };
     objects.add(Box::new(Sphere {
         center: Point3 { e: (0.0, 0.0, 0.0) },
@@ -29,6 +32,7 @@ pub fn earth() -> (Color, f64, u32, HittableList, Camera) {
         aspect_ratio,
         1600,
         objects,
+        HittableList::new(),
         Camera::new_cam(
             lookfrom,
             lookat,
@@ -1,6 +1,6 @@
 use crate::camera::{Camera, NewCamMessage};
 //这是book2的最后一张图
-use crate::randoms::{random_double, random_vec};
+use crate::randoms::Rng;
 use crate::{
     bvh::BvhNode,
     hittable::movingsphere::MovingSphere,
@@ -12,17 +12,21 @@ use crate::{
     hittable::{flipface::FlipFace, HittableList},
     material::{
         dielectric::Dielectric, diffuselight::DiffuseLight, lambertian::Lambertian, metal::Metal,
+        WithId,
     },
     perlin::Perlin,
     texture::{imagetexture::ImageTexture, noisetexture::NoiseTexture, solodcolor::SolidColor},
     vec3::{Color, Point3, Vec3},
 };
-pub fn final_scene() -> (Color, f64, u32, HittableList, Camera) {
+pub fn final_scene(rng: &mut Rng) -> (Color, f64, u32, HittableList, HittableList, Camera) {
     let mut boxes1 = HittableList::new();
-    let ground = Lambertian {
-        albedo: SolidColor::new(Color {
-            e: (0.48, 0.83, 0.53),
-        }),
+    let ground = WithId {
+        id: 1,
+        mat: Lambertian {
+            albedo: SolidColor::new(Color {
+                e: (0.48, 0.83, 0.53),
+            }),
+        },
     };
     let box_per_side = 20;
     for i in 0..box_per_side {
@@ -32,7 +36,7 @@ pub fn final_scene() -> (Color, f64, u32, HittableList, Camera) {
             let z0 = -1000.0 + (j as f64) * w;
             let y0 = 0.0;
             let x1 = x0 + w;
-            let y1 = random_double(1.0, 101.0);
+            let y1 = rng.random_double(1.0, 101.0);
             let z1 = z0 + w;
             boxes1.add(Box::new(MyBox::new(
                 &Point3 { e: (x0, y0, z0) },
@@ -50,7 +54,10 @@ pub fn final_scene() -> (Color, f64, u32, HittableList, Camera) {
         0.0,
         1.0,
     )));
-    let light = DiffuseLight::new(Color { e: (7.0, 7.0, 7.0) });
+    let light = WithId {
+        id: 2,
+        mat: DiffuseLight::new(Color { e: (7.0, 7.0, 7.0) }),
+    };
     objects.add(Box::new(FlipFace {
         ptr: XzRect {
             x0: 123.0,
@@ -58,9 +65,18 @@ pub fn final_scene() -> (Color, f64, u32, HittableList, Camera) {
             z0: 147.0,
             z1: 412.0,
             k: 554.0,
-            mp: light,
+            mp: light.clone(),
         },
     }));
+    let mut lights = HittableList::new();
+    lights.add(Box::new(XzRect {
+        x0: 123.0,
+        x1: 423.0,
+        z0: 147.0,
+        z1: 412.0,
+        k: 554.0,
+        mp: light,
+    }));
     let center1_ = Point3 {
         e: (400.0, 400.0, 200.0),
     };
@@ -68,9 +84,12 @@ pub fn final_scene() -> (Color, f64, u32, HittableList, Camera) {
         + Vec3 {
             e: (30.0, 0.0, 0.0),
         };
-    let moving_sphere_material = Lambertian {
-        albedo: SolidColor {
-            color_value: Color { e: (0.7, 0.3, 0.1) },
+    let moving_sphere_material = WithId {
+        id: 3,
+        mat: Lambertian {
+            albedo: SolidColor {
+                color_value: Color { e: (0.7, 0.3, 0.1) },
+            },
         },
     };
     objects.add(Box::new(MovingSphere {
@@ -86,16 +105,22 @@ pub fn final_scene() -> (Color, f64, u32, HittableList, Camera) {
             e: (260.0, 150.0, 45.0),
         },
         radius: 50.0,
-        mat_ptr: Dielectric { ir: 1.5 },
+        mat_ptr: WithId {
+            id: 4,
+            mat: Dielectric::new(1.5),
+        },
     }));
     objects.add(Box::new(Sphere {
         center: Point3 {
             e: (0.0, 150.0, 145.0),
         },
         radius: 50.0,
-        mat_ptr: Metal {
-            albedo: Color { e: (0.8, 0.8, 0.9) },
-            fuzz: 1.0,
+        mat_ptr: WithId {
+            id: 5,
+            mat: Metal {
+                albedo: Color { e: (0.8, 0.8, 0.9) },
+                fuzz: 1.0,
+            },
         },
     }));
     let boundary = Box::new(Sphere {
@@ -103,7 +128,10 @@ pub fn final_scene() -> (Color, f64, u32, HittableList, Camera) {
             e: (360.0, 150.0, 145.0),
         },
         radius: 70.0,
-        mat_ptr: Dielectric { ir: 1.5 },
+        mat_ptr: WithId {
+            id: 4,
+            mat: Dielectric::new(1.5),
+        },
     });
     objects.add(boundary);
     let mut boundary = Sphere {
@@ -111,7 +139,7 @@ pub fn final_scene() -> (Color, f64, u32, HittableList, Camera) {
             e: (360.0, 150.0, 145.0),
         },
         radius: 70.0,
-        mat_ptr: Dielectric { ir: 1.5 },
+        mat_ptr: Dielectric::new(1.5),
     };
     objects.add(Box::new(ConstantMedium::new(
         boundary,
@@ -121,7 +149,7 @@ pub fn final_scene() -> (Color, f64, u32, HittableList, Camera) {
     boundary = Sphere {
         center: Point3 { e: (0.0, 0.0, 0.0) },
         radius: 5000.0,
-        mat_ptr: Dielectric { ir: 1.5 },
+        mat_ptr: Dielectric::new(1.5),
     };
     objects.add(Box::new(ConstantMedium::new(
         boundary,
@@ -129,8 +157,11 @@ pub fn final_scene() -> (Color, f64, u32, HittableList, Camera) {
         SolidColor::new(Color { e: (1.0, 1.0, 1.0) }),
     )));
     let path = std::path::Path::new("raytracer/src/sourcepictures/earthmap.jpg");
-    let emat = Lambertian {
-        albedo: ImageTexture::new(path),
+    let emat = WithId {
+        id: 6,
+        mat: Lambertian {
+            albedo: ImageTexture::new(path),
+        },
     };
     objects.add(Box::new(Sphere {
         center: Point3 {
@@ -140,7 +171,7 @@ pub fn final_scene() -> (Color, f64, u32, HittableList, Camera) {
         mat_ptr: emat,
     }));
     let pertext = NoiseTexture {
-        noise: Perlin::new(),
+        noise: Perlin::new(rng),
         scale: 0.1,
     };
     objects.add(Box::new(Sphere {
@@ -148,7 +179,10 @@ pub fn final_scene() -> (Color, f64, u32, HittableList, Camera) {
             e: (220.0, 280.0, 300.0),
         },
         radius: 80.0,
-        mat_ptr: Lambertian { albedo: pertext },
+        mat_ptr: WithId {
+            id: 7,
+            mat: Lambertian { albedo: pertext },
+        },
     }));
 
     let mut boxes2 = HittableList::new();
@@ -156,14 +190,17 @@ pub fn final_scene() -> (Color, f64, u32, HittableList, Camera) {
     let ns = 1000;
 
     for _j in 0..ns {
-        let white = Lambertian {
-            albedo: SolidColor::new(Color {
-                e: (0.73, 0.73, 0.73),
-            }),
+        let white = WithId {
+            id: 8,
+            mat: Lambertian {
+                albedo: SolidColor::new(Color {
+                    e: (0.73, 0.73, 0.73),
+                }),
+            },
         };
 
         boxes2.add(Box::new(Sphere {
-            center: random_vec(0.0, 165.0),
+            center: rng.random_vec(0.0, 165.0),
             radius: 10.0,
             mat_ptr: white,
         }))
@@ -190,6 +227,7 @@ pub fn final_scene() -> (Color, f64, u32, HittableList, Camera) {
         1.0,
         600,
         objects,
+        lights,
         Camera::new_cam(
             lookfrom,
             lookat,
@@ -4,6 +4,39 @@ pub mod earth;
 pub mod finalscene;
 pub mod myworld;
 pub mod randomscene;
+pub mod scenefile;
 pub mod simplelight;
 pub mod twoperlinsphere;
 pub mod twosphere;
+
+use crate::{camera::Camera, hittable::HittableList, randoms::Rng, vec3::Color};
+
+pub const BUILTIN_SCENES: [&str; 9] = [
+    "cornell_box",
+    "cornell_box_smoke",
+    "earth",
+    "final_scene",
+    "my_world",
+    "random_scene",
+    "simple_light",
+    "two_perlin_sphere",
+    "two_spheres",
+];
+//随机场景、Perlin 噪声与 BVH 的划分轴都从 rng 取随机数
+pub fn builtin_scene(
+    name: &str,
+    rng: &mut Rng,
+) -> Option<(Color, f64, u32, HittableList, HittableList, Camera)> {
+    match name {
+        "cornell_box" => Some(cornellbox::cornell_box()),
+        "cornell_box_smoke" => Some(cornellboxsmoke::cornell_box_smoke()),
+        "earth" => Some(earth::earth()),
+        "final_scene" => Some(finalscene::final_scene(rng)),
+        "my_world" => Some(myworld::my_world()),
+        "random_scene" => Some(randomscene::random_scene(rng)),
+        "simple_light" => Some(simplelight::simple_light(rng)),
+        "two_perlin_sphere" => Some(twoperlinsphere::two_perlin_sphere(rng)),
+        "two_spheres" => Some(twosphere::two_spheres()),
+        _ => None,
+    }
+}
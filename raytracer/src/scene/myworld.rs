@@ -1,3 +1,7 @@
+use std::process::exit;
+
+use console::style;
+
 use crate::{
     camera::{Camera, NewCamMessage},
     hittable::HittableList,
@@ -5,15 +9,22 @@ use crate::{
     loadobj::obj_mtl_load,
     vec3::{Color, Point3, Vec3},
 };
-pub fn my_world() -> (Color, f64, u32, HittableList, Camera) {
+pub fn my_world() -> (Color, f64, u32, HittableList, HittableList, Camera) {
     let mut objects = HittableList::new();
     let battery: String = "1".to_string();
+    let model = match obj_mtl_load(&battery) {
+        Ok(model) => model,
+        Err(e) => {
+            println!("{}", style(e).red());
+            exit(1);
+        }
+    };
     objects.add(Box::new(RotateY::new(
         Translate {
             offset: Vec3 {
                 e: (500.0, 50.0, 0.0),
             },
-            ptr: obj_mtl_load(&battery),
+            ptr: model,
         },
         35.0,
     )));
@@ -29,6 +40,7 @@ pub fn my_world() -> (Color, f64, u32, HittableList, Camera) {
         1.0,
         600,
         objects,
+        HittableList::new(),
         Camera::new_cam(
             lookfrom,
             lookat,
@@ -1,20 +1,25 @@
-use crate::randoms::{random_double, random_vec};
+use crate::randoms::Rng;
 use crate::{
     camera::{Camera, NewCamMessage},
     hittable::sphere::Sphere,
     hittable::HittableList,
-    material::{dielectric::Dielectric, lambertian::Lambertian, metal::Metal},
+    material::{dielectric::Dielectric, lambertian::Lambertian, metal::Metal, WithId},
     texture::{checkertexture::CheckerTexture, solodcolor::SolidColor},
     vec3::{Color, Point3, Vec3},
 };
 
-pub fn random_scene() -> (Color, f64, u32, HittableList, Camera) {
+pub fn random_scene(rng: &mut Rng) -> (Color, f64, u32, HittableList, HittableList, Camera) {
     let mut world = HittableList::new();
     let checker = CheckerTexture {
         even: SolidColor::new(Color { e: (0.2, 0.3, 0.1) }),
         odd: SolidColor::new(Color { e: (0.9, 0.9, 0.9) }),
     };
-    let ground_material = Lambertian { albedo: checker };
+    let ground_material = WithId {
+        id: 1,
+        mat: Lambertian { albedo: checker },
+    };
+    //每个小球的材质都不同，各自编号
+    let mut material_id = 1;
     world.add(Box::new(Sphere {
         center: Point3 {
             e: (0.0, -1000.0, 0.0),
@@ -24,17 +29,18 @@ pub fn random_scene() -> (Color, f64, u32, HittableList, Camera) {
     }));
     for a in -11..11 {
         for b in -11..11 {
-            let choose_mat = random_double(0.0, 1.0);
+            let choose_mat = rng.random_double(0.0, 1.0);
             let center_: Point3 = Point3 {
                 e: (
-                    (a as f64) + 0.9 * random_double(0.0, 1.0),
+                    (a as f64) + 0.9 * rng.random_double(0.0, 1.0),
                     0.2,
-                    (b as f64) + 0.9 * random_double(0.0, 1.0),
+                    (b as f64) + 0.9 * rng.random_double(0.0, 1.0),
                 ),
             };
             if (center_ - Point3 { e: (4.0, 0.2, 0.0) }).length() > 0.9 {
+                material_id += 1;
                 if choose_mat < 0.8 {
-                    let albedo_ = random_vec(0.0, 1.0) * random_vec(0.0, 1.0);
+                    let albedo_ = rng.random_vec(0.0, 1.0) * rng.random_vec(0.0, 1.0);
                     /*let center2 = center_
                     + Vec3 {
                         e: (0.0, random_double(0.0, 0.5), 0.0),
@@ -47,11 +53,14 @@ pub fn random_scene() -> (Color, f64, u32, HittableList, Camera) {
                     world.add(Box::new(Sphere {
                         center: center_,
                         radius: 0.2,
-                        mat_ptr: sphere_material,
+                        mat_ptr: WithId {
+                            id: material_id,
+                            mat: sphere_material,
+                        },
                     }));
                 } else if choose_mat < 0.95 {
-                    let albedo_ = random_vec(0.5, 1.0);
-                    let fuzz_ = random_double(0.0, 0.5);
+                    let albedo_ = rng.random_vec(0.5, 1.0);
+                    let fuzz_ = rng.random_double(0.0, 0.5);
                     let sphere_material = Metal {
                         albedo: albedo_,
                         fuzz: fuzz_,
@@ -59,24 +68,33 @@ pub fn random_scene() -> (Color, f64, u32, HittableList, Camera) {
                     world.add(Box::new(Sphere {
                         center: center_,
                         radius: 0.2,
-                        mat_ptr: sphere_material,
+                        mat_ptr: WithId {
+                            id: material_id,
+                            mat: sphere_material,
+                        },
                     }));
                 } else {
-                    let sphere_material = Dielectric { ir: 1.5 };
+                    let sphere_material = Dielectric::new(1.5);
                     world.add(Box::new(Sphere {
                         center: center_,
                         radius: 0.2,
-                        mat_ptr: sphere_material,
+                        mat_ptr: WithId {
+                            id: material_id,
+                            mat: sphere_material,
+                        },
                     }));
                 }
             }
         }
     }
-    let material1 = Dielectric { ir: 1.5 };
+    let material1 = Dielectric::new(1.5);
     world.add(Box::new(Sphere {
         center: Point3 { e: (0.0, 1.0, 0.0) },
         radius: 1.0,
-        mat_ptr: material1,
+        mat_ptr: WithId {
+            id: material_id + 1,
+            mat: material1,
+        },
     }));
     let material2 = Lambertian {
         albedo: SolidColor {
@@ -88,7 +106,10 @@ pub fn random_scene() -> (Color, f64, u32, HittableList, Camera) {
             e: (-4.0, 1.0, 0.0),
         },
         radius: 1.0,
-        mat_ptr: material2,
+        mat_ptr: WithId {
+            id: material_id + 2,
+            mat: material2,
+        },
     }));
     let material3 = Metal {
         albedo: Color { e: (0.7, 0.6, 0.5) },
@@ -97,7 +118,10 @@ pub fn random_scene() -> (Color, f64, u32, HittableList, Camera) {
     world.add(Box::new(Sphere {
         center: Point3 { e: (4.0, 1.0, 0.0) },
         radius: 1.0,
-        mat_ptr: material3,
+        mat_ptr: WithId {
+            id: material_id + 3,
+            mat: material3,
+        },
     }));
     let lookfrom: Point3 = Point3 {
         e: (13.0, 2.0, 3.0),
@@ -109,6 +133,7 @@ pub fn random_scene() -> (Color, f64, u32, HittableList, Camera) {
         aspect_ratio,
         1600,
         world,
+        HittableList::new(),
         Camera::new_cam(
             lookfrom,
             lookat,
@@ -0,0 +1,824 @@
+use std::{collections::HashMap, path::Path, sync::Arc, time::Instant};
+
+use serde::Deserialize;
+
+use crate::{
+    animation::interpolate,
+    bvh::BvhNode,
+    camera::{Camera, NewCamMessage},
+    hittable::{
+        constantmedium::ConstantMedium, flipface::FlipFace,
+        heterogeneousmedium::HeterogeneousMedium, movingsphere::MovingSphere, mybox::MyBox,
+        rect::XyRect, rect::XzRect, rect::YzRect, rotate::RotateY, sphere::Sphere,
+        translate::Translate, triangle::Triangle, Hittable, HittableList,
+    },
+    loadobj::obj_mtl_load,
+    material::{
+        dielectric::Dielectric, diffuselight::DiffuseLight, isotropic::Isotropic,
+        lambertian::Lambertian, metal::Metal, Material, WithId,
+    },
+    perlin::Perlin,
+    randoms::Rng,
+    spectrum::Dispersion,
+    texture::{
+        checkertexture::CheckerTexture,
+        density::{DensityField, GradientDensity, GridDensity, NoiseDensity},
+        imagetexture::ImageTexture,
+        noisetexture::NoiseTexture,
+        solodcolor::SolidColor,
+        Texture,
+    },
+    vec3::{Color, Vec3},
+};
+
+//场景文件的格式，json 与 yaml 共用
+#[derive(Deserialize)]
+pub struct SceneFile {
+    #[serde(default)]
+    pub background: [f64; 3],
+    pub aspect_ratio: f64,
+    pub width: u32,
+    pub camera: CameraDesc,
+    #[serde(default)]
+    pub textures: HashMap<String, TextureDesc>,
+    #[serde(default)]
+    pub materials: HashMap<String, MaterialDesc>,
+    pub objects: Vec<ObjectDesc>,
+    //需要重点采样的光源，只用到几何形状
+    #[serde(default)]
+    pub lights: Vec<ObjectDesc>,
+}
+
+#[derive(Deserialize)]
+pub struct CameraDesc {
+    pub lookfrom: [f64; 3],
+    pub lookat: [f64; 3],
+    #[serde(default = "default_vup")]
+    pub vup: [f64; 3],
+    pub vfov: f64,
+    #[serde(default)]
+    pub aperture: f64,
+    #[serde(default = "default_focus_dist")]
+    pub focus_dist: f64,
+    #[serde(default)]
+    pub time0: f64,
+    #[serde(default = "default_time1")]
+    pub time1: f64,
+    //按帧插值的相机参数，覆盖上面的同名参数
+    #[serde(default)]
+    pub keyframes: Vec<CameraKey>,
+}
+fn default_vup() -> [f64; 3] {
+    [0.0, 1.0, 0.0]
+}
+fn default_focus_dist() -> f64 {
+    10.0
+}
+fn default_time1() -> f64 {
+    1.0
+}
+fn default_noise_depth() -> i32 {
+    7
+}
+
+//某一帧的相机参数，没写的参数不参与该参数的插值
+#[derive(Deserialize)]
+pub struct CameraKey {
+    pub frame: f64,
+    pub lookfrom: Option<[f64; 3]>,
+    pub lookat: Option<[f64; 3]>,
+    pub vfov: Option<f64>,
+    pub focus_dist: Option<f64>,
+}
+
+//某一帧物体的变换：先绕 y 轴旋转 rotate_y 度，再平移 translate
+#[derive(Deserialize)]
+pub struct TransformKey {
+    pub frame: f64,
+    pub translate: Option<[f64; 3]>,
+    pub rotate_y: Option<f64>,
+}
+
+//纹理可以直接写颜色、引用命名纹理或者内联定义
+#[derive(Deserialize)]
+#[serde(untagged)]
+pub enum TextureDesc {
+    Color([f64; 3]),
+    Named(String),
+    Inline(TextureKind),
+}
+#[derive(Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum TextureKind {
+    Solid {
+        color: [f64; 3],
+    },
+    Checker {
+        odd: Box<TextureDesc>,
+        even: Box<TextureDesc>,
+    },
+    Image {
+        path: String,
+    },
+    Noise {
+        scale: f64,
+    },
+}
+
+//非均匀介质的密度场
+#[derive(Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum DensityDesc {
+    Noise {
+        scale: f64,
+        density: f64,
+        #[serde(default = "default_noise_depth")]
+        depth: i32,
+        #[serde(default)]
+        cutoff: f64,
+    },
+    Gradient {
+        from: [f64; 3],
+        to: [f64; 3],
+        density0: f64,
+        density1: f64,
+    },
+    //values 按 x 最快、z 最慢排列
+    Grid {
+        min: [f64; 3],
+        max: [f64; 3],
+        resolution: [usize; 3],
+        values: Vec<f64>,
+    },
+}
+
+#[derive(Deserialize)]
+#[serde(untagged)]
+pub enum MaterialRef {
+    Named(String),
+    Inline(MaterialDesc),
+}
+#[derive(Deserialize)]
+pub struct SellmeierDesc {
+    pub b: [f64; 3],
+    pub c: [f64; 3],
+}
+#[derive(Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum MaterialDesc {
+    Lambertian {
+        albedo: TextureDesc,
+    },
+    Metal {
+        albedo: [f64; 3],
+        fuzz: f64,
+    },
+    //折射率给 ir，或者给 cauchy 的 [a, b]、sellmeier 的 b 与 c（波长以 µm 计）使其随波长变化
+    Dielectric {
+        #[serde(default)]
+        ir: Option<f64>,
+        #[serde(default)]
+        cauchy: Option<[f64; 2]>,
+        #[serde(default)]
+        sellmeier: Option<SellmeierDesc>,
+    },
+    DiffuseLight {
+        emit: TextureDesc,
+    },
+    Isotropic {
+        albedo: TextureDesc,
+    },
+}
+
+#[derive(Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum ObjectDesc {
+    Sphere {
+        center: [f64; 3],
+        radius: f64,
+        material: MaterialRef,
+    },
+    MovingSphere {
+        center0: [f64; 3],
+        center1: [f64; 3],
+        #[serde(default)]
+        time0: f64,
+        #[serde(default = "default_time1")]
+        time1: f64,
+        radius: f64,
+        material: MaterialRef,
+    },
+    XyRect {
+        x0: f64,
+        x1: f64,
+        y0: f64,
+        y1: f64,
+        k: f64,
+        material: MaterialRef,
+    },
+    XzRect {
+        x0: f64,
+        x1: f64,
+        z0: f64,
+        z1: f64,
+        k: f64,
+        material: MaterialRef,
+    },
+    YzRect {
+        y0: f64,
+        y1: f64,
+        z0: f64,
+        z1: f64,
+        k: f64,
+        material: MaterialRef,
+    },
+    Box {
+        p0: [f64; 3],
+        p1: [f64; 3],
+        material: MaterialRef,
+    },
+    Triangle {
+        a: [f64; 3],
+        b: [f64; 3],
+        c: [f64; 3],
+        #[serde(default)]
+        uv: Option<[[f64; 2]; 3]>,
+        material: MaterialRef,
+    },
+    ConstantMedium {
+        boundary: Box<ObjectDesc>,
+        density: f64,
+        albedo: TextureDesc,
+    },
+    HeterogeneousMedium {
+        boundary: Box<ObjectDesc>,
+        density: DensityDesc,
+        albedo: TextureDesc,
+    },
+    FlipFace {
+        object: Box<ObjectDesc>,
+    },
+    RotateY {
+        angle: f64,
+        object: Box<ObjectDesc>,
+    },
+    Translate {
+        offset: [f64; 3],
+        object: Box<ObjectDesc>,
+    },
+    Bvh {
+        objects: Vec<ObjectDesc>,
+    },
+    Obj {
+        name: String,
+    },
+    //按关键帧插值的旋转与平移
+    Animated {
+        keyframes: Vec<TransformKey>,
+        object: Box<ObjectDesc>,
+    },
+}
+
+impl ObjectDesc {
+    //子树里有关键帧
+    fn animated(&self) -> bool {
+        let mut frames = vec![];
+        self.key_frames(&mut frames);
+        !frames.is_empty()
+    }
+    //所有关键帧的帧号
+    fn key_frames(&self, out: &mut Vec<f64>) {
+        match self {
+            ObjectDesc::ConstantMedium { boundary: o, .. }
+            | ObjectDesc::HeterogeneousMedium { boundary: o, .. }
+            | ObjectDesc::FlipFace { object: o }
+            | ObjectDesc::RotateY { object: o, .. }
+            | ObjectDesc::Translate { object: o, .. } => o.key_frames(out),
+            ObjectDesc::Bvh { objects } => {
+                for o in objects {
+                    o.key_frames(out);
+                }
+            }
+            ObjectDesc::Animated { keyframes, object } => {
+                out.extend(keyframes.iter().map(|k| k.frame));
+                object.key_frames(out);
+            }
+            _ => {}
+        }
+    }
+}
+
+impl SceneFile {
+    //有物体关键帧时每一帧都要重建场景，否则只需换相机
+    pub fn animates_objects(&self) -> bool {
+        self.objects
+            .iter()
+            .chain(self.lights.iter())
+            .any(|o| o.animated())
+    }
+    //相机与物体关键帧覆盖的帧范围，没有关键帧时为 None
+    pub fn frame_range(&self) -> Option<(u32, u32)> {
+        let mut frames: Vec<f64> = self.camera.keyframes.iter().map(|k| k.frame).collect();
+        for o in self.objects.iter().chain(self.lights.iter()) {
+            o.key_frames(&mut frames);
+        }
+        let lo = frames.iter().copied().fold(f64::INFINITY, f64::min);
+        let hi = frames.iter().copied().fold(f64::NEG_INFINITY, f64::max);
+        if frames.is_empty() {
+            None
+        } else {
+            Some((lo.max(0.0).floor() as u32, hi.max(0.0).ceil() as u32))
+        }
+    }
+    //第 frame 帧的相机
+    pub fn camera_at(&self, frame: f64) -> Camera {
+        let cam = &self.camera;
+        let keys = &cam.keyframes;
+        let lookfrom = interpolate(
+            keys.iter().filter_map(|k| Some((k.frame, k.lookfrom?))),
+            frame,
+        )
+        .unwrap_or(cam.lookfrom);
+        let lookat = interpolate(
+            keys.iter().filter_map(|k| Some((k.frame, k.lookat?))),
+            frame,
+        )
+        .unwrap_or(cam.lookat);
+        let vfov = interpolate(keys.iter().filter_map(|k| Some((k.frame, k.vfov?))), frame)
+            .unwrap_or(cam.vfov);
+        let focus_dist = interpolate(
+            keys.iter().filter_map(|k| Some((k.frame, k.focus_dist?))),
+            frame,
+        )
+        .unwrap_or(cam.focus_dist);
+        Camera::new_cam(
+            vec3(lookfrom),
+            vec3(lookat),
+            vec3(cam.vup),
+            NewCamMessage {
+                vfov,
+                _aspect_ratio: self.aspect_ratio,
+                aperture: cam.aperture,
+                focus_dist,
+                _time0: cam.time0,
+                _time1: cam.time1,
+            },
+        )
+    }
+}
+
+fn vec3(a: [f64; 3]) -> Vec3 {
+    Vec3 {
+        e: (a[0], a[1], a[2]),
+    }
+}
+
+//动画各帧之间留下的物体：不含关键帧的子树（OBJ 模型、嵌套的 BVH 等）只在第一帧建一次，之后各帧共用，
+//每帧只重建带关键帧的变换。记下建它时的用时、用掉的材质编号个数与建完后的随机数，
+//复用时照样推进，结果与重建相同。以 SceneFile 里描述的地址为键，只能配同一个 SceneFile 使用
+#[derive(Default)]
+pub struct SceneCache {
+    objects: HashMap<*const ObjectDesc, CachedObject>,
+    //最近一次 build_scene 复用的物体个数，以及它们当初建造的用时
+    pub reused: usize,
+    pub saved_seconds: f64,
+}
+struct CachedObject {
+    object: Arc<dyn Hittable>,
+    seconds: f64,
+    materials: u32,
+    rng: Rng,
+}
+
+struct SceneBuilder<'a> {
+    texture_descs: &'a HashMap<String, TextureDesc>,
+    textures: HashMap<String, Arc<dyn Texture + Send + Sync>>,
+    materials: HashMap<String, Arc<dyn Material + Send + Sync>>,
+    //正在展开的命名纹理，用来发现循环引用
+    resolving: Vec<String>,
+    //材质编号从 1 开始：先按名字排序给命名材质，再按出现顺序给内联材质
+    material_count: u32,
+    //噪声纹理与 BVH 的划分轴从这里取随机数
+    rng: &'a mut Rng,
+    time0: f64,
+    time1: f64,
+    frame: f64,
+    cache: &'a mut SceneCache,
+    //只有物体带关键帧的场景才缓存，缓存的子树内部不再逐个缓存
+    caching: bool,
+}
+fn density_field(
+    desc: &DensityDesc,
+    rng: &mut Rng,
+) -> Result<Arc<dyn DensityField + Send + Sync>, String> {
+    Ok(match desc {
+        DensityDesc::Noise {
+            scale,
+            density,
+            depth,
+            cutoff,
+        } => {
+            if !(0.0..1.0).contains(cutoff) {
+                return Err(format!("noise density cutoff {} is not in [0, 1)", cutoff));
+            }
+            Arc::new(NoiseDensity {
+                noise: Perlin::new(rng),
+                scale: *scale,
+                depth: *depth,
+                cutoff: *cutoff,
+                density: *density,
+            })
+        }
+        DensityDesc::Gradient {
+            from,
+            to,
+            density0,
+            density1,
+        } => {
+            if from == to {
+                return Err("gradient density needs distinct from and to".to_string());
+            }
+            Arc::new(GradientDensity {
+                from: vec3(*from),
+                to: vec3(*to),
+                density0: *density0,
+                density1: *density1,
+            })
+        }
+        DensityDesc::Grid {
+            min,
+            max,
+            resolution,
+            values,
+        } => Arc::new(
+            GridDensity::new(vec3(*min), vec3(*max), *resolution, values.clone()).ok_or(
+                format!(
+                    "grid density needs at least 2 points per axis and {} values",
+                    resolution.iter().product::<usize>()
+                ),
+            )?,
+        ),
+    })
+}
+
+impl<'a> SceneBuilder<'a> {
+    fn named_texture(&mut self, name: &str) -> Result<Arc<dyn Texture + Send + Sync>, String> {
+        if let Some(tex) = self.textures.get(name) {
+            return Ok(tex.clone());
+        }
+        //还在展开的纹理又被引用到，说明纹理之间循环引用
+        if let Some(k) = self.resolving.iter().position(|n| n == name) {
+            let mut cycle = self.resolving[k..].join("\" -> \"");
+            cycle.push_str("\" -> \"");
+            cycle.push_str(name);
+            return Err(format!(
+                "texture \"{}\" is defined in terms of itself (\"{}\")",
+                name, cycle
+            ));
+        }
+        let desc = self
+            .texture_descs
+            .get(name)
+            .ok_or(format!("unknown texture \"{}\"", name))?;
+        self.resolving.push(name.to_string());
+        let tex = self.texture(desc);
+        self.resolving.pop();
+        let tex = tex?;
+        self.textures.insert(name.to_string(), tex.clone());
+        Ok(tex)
+    }
+    fn texture(&mut self, desc: &TextureDesc) -> Result<Arc<dyn Texture + Send + Sync>, String> {
+        match desc {
+            TextureDesc::Color(c) => Ok(Arc::new(SolidColor::new(vec3(*c)))),
+            TextureDesc::Named(name) => self.named_texture(name),
+            TextureDesc::Inline(kind) => self.texture_kind(kind),
+        }
+    }
+    fn texture_kind(
+        &mut self,
+        kind: &TextureKind,
+    ) -> Result<Arc<dyn Texture + Send + Sync>, String> {
+        Ok(match kind {
+            TextureKind::Solid { color } => Arc::new(SolidColor::new(vec3(*color))),
+            TextureKind::Checker { odd, even } => Arc::new(CheckerTexture {
+                odd: self.texture(odd)?,
+                even: self.texture(even)?,
+            }),
+            TextureKind::Image { path } => Arc::new(ImageTexture::new(Path::new(path))),
+            TextureKind::Noise { scale } => Arc::new(NoiseTexture {
+                noise: Perlin::new(self.rng),
+                scale: *scale,
+            }),
+        })
+    }
+    fn material_desc(
+        &mut self,
+        desc: &MaterialDesc,
+    ) -> Result<Arc<dyn Material + Send + Sync>, String> {
+        let mat: Arc<dyn Material + Send + Sync> = match desc {
+            MaterialDesc::Lambertian { albedo } => Arc::new(Lambertian {
+                albedo: self.texture(albedo)?,
+            }),
+            MaterialDesc::Metal { albedo, fuzz } => Arc::new(Metal {
+                albedo: vec3(*albedo),
+                fuzz: *fuzz,
+            }),
+            MaterialDesc::Dielectric {
+                ir,
+                cauchy,
+                sellmeier,
+            } => Arc::new(match (ir, cauchy, sellmeier) {
+                (Some(ir), None, None) => Dielectric::new(*ir),
+                (None, Some([a, b]), None) => {
+                    Dielectric::dispersive(Dispersion::Cauchy { a: *a, b: *b })
+                }
+                (None, None, Some(s)) => {
+                    Dielectric::dispersive(Dispersion::Sellmeier { b: s.b, c: s.c })
+                }
+                _ => {
+                    return Err(
+                        "dielectric needs exactly one of ir, cauchy and sellmeier".to_string()
+                    )
+                }
+            }),
+            MaterialDesc::DiffuseLight { emit } => Arc::new(DiffuseLight {
+                emit: self.texture(emit)?,
+            }),
+            MaterialDesc::Isotropic { albedo } => Arc::new(Isotropic {
+                albedo: self.texture(albedo)?,
+            }),
+        };
+        self.material_count += 1;
+        Ok(Arc::new(WithId {
+            id: self.material_count,
+            mat,
+        }))
+    }
+    fn material(&mut self, r: &MaterialRef) -> Result<Arc<dyn Material + Send + Sync>, String> {
+        match r {
+            MaterialRef::Named(name) => self
+                .materials
+                .get(name)
+                .cloned()
+                .ok_or(format!("unknown material \"{}\"", name)),
+            MaterialRef::Inline(desc) => self.material_desc(desc),
+        }
+    }
+    fn object(&mut self, desc: &ObjectDesc) -> Result<Box<dyn Hittable>, String> {
+        if !self.caching || desc.animated() {
+            return self.build_object(desc);
+        }
+        let key = desc as *const ObjectDesc;
+        if let Some(cached) = self.cache.objects.get(&key) {
+            self.material_count += cached.materials;
+            *self.rng = cached.rng;
+            self.cache.reused += 1;
+            self.cache.saved_seconds += cached.seconds;
+            return Ok(Box::new(cached.object.clone()));
+        }
+        let (start, materials) = (Instant::now(), self.material_count);
+        self.caching = false;
+        let object = self.build_object(desc);
+        self.caching = true;
+        let object: Arc<dyn Hittable> = Arc::from(object?);
+        self.cache.objects.insert(
+            key,
+            CachedObject {
+                object: object.clone(),
+                seconds: start.elapsed().as_secs_f64(),
+                materials: self.material_count - materials,
+                rng: *self.rng,
+            },
+        );
+        Ok(Box::new(object))
+    }
+    fn build_object(&mut self, desc: &ObjectDesc) -> Result<Box<dyn Hittable>, String> {
+        Ok(match desc {
+            ObjectDesc::Sphere {
+                center,
+                radius,
+                material,
+            } => Box::new(Sphere {
+                center: vec3(*center),
+                radius: *radius,
+                mat_ptr: self.material(material)?,
+            }),
+            ObjectDesc::MovingSphere {
+                center0,
+                center1,
+                time0,
+                time1,
+                radius,
+                material,
+            } => Box::new(MovingSphere {
+                center0: vec3(*center0),
+                center1: vec3(*center1),
+                time0: *time0,
+                time1: *time1,
+                radius: *radius,
+                mat_ptr: self.material(material)?,
+            }),
+            ObjectDesc::XyRect {
+                x0,
+                x1,
+                y0,
+                y1,
+                k,
+                material,
+            } => Box::new(XyRect {
+                x0: *x0,
+                x1: *x1,
+                y0: *y0,
+                y1: *y1,
+                k: *k,
+                mp: self.material(material)?,
+            }),
+            ObjectDesc::XzRect {
+                x0,
+                x1,
+                z0,
+                z1,
+                k,
+                material,
+            } => Box::new(XzRect {
+                x0: *x0,
+                x1: *x1,
+                z0: *z0,
+                z1: *z1,
+                k: *k,
+                mp: self.material(material)?,
+            }),
+            ObjectDesc::YzRect {
+                y0,
+                y1,
+                z0,
+                z1,
+                k,
+                material,
+            } => Box::new(YzRect {
+                y0: *y0,
+                y1: *y1,
+                z0: *z0,
+                z1: *z1,
+                k: *k,
+                mp: self.material(material)?,
+            }),
+            ObjectDesc::Box { p0, p1, material } => {
+                Box::new(MyBox::new(&vec3(*p0), &vec3(*p1), self.material(material)?))
+            }
+            ObjectDesc::Triangle {
+                a,
+                b,
+                c,
+                uv,
+                material,
+            } => {
+                let uv = uv.unwrap_or([[0.0, 0.0], [0.0, 1.0], [1.0, 0.0]]);
+                Box::new(Triangle::new(
+                    vec3(*a),
+                    vec3(*b),
+                    vec3(*c),
+                    self.material(material)?,
+                    (uv[0][0], uv[0][1]),
+                    (uv[1][0], uv[1][1]),
+                    (uv[2][0], uv[2][1]),
+                ))
+            }
+            ObjectDesc::ConstantMedium {
+                boundary,
+                density,
+                albedo,
+            } => Box::new(ConstantMedium::new(
+                self.object(boundary)?,
+                *density,
+                self.texture(albedo)?,
+            )),
+            ObjectDesc::HeterogeneousMedium {
+                boundary,
+                density,
+                albedo,
+            } => Box::new(HeterogeneousMedium::new(
+                self.object(boundary)?,
+                density_field(density, self.rng)?,
+                self.texture(albedo)?,
+            )),
+            ObjectDesc::FlipFace { object } => Box::new(FlipFace {
+                ptr: self.object(object)?,
+            }),
+            ObjectDesc::RotateY { angle, object } => {
+                Box::new(RotateY::new(self.object(object)?, *angle))
+            }
+            ObjectDesc::Translate { offset, object } => Box::new(Translate {
+                offset: vec3(*offset),
+                ptr: self.object(object)?,
+            }),
+            ObjectDesc::Bvh { objects } => {
+                if objects.is_empty() {
+                    return Err("bvh must contain at least one object".to_string());
+                }
+                let mut list = Vec::new();
+                for o in objects {
+                    list.push(self.object(o)?);
+                }
+                let end = list.len() as u32;
+                Box::new(BvhNode::new_nodes(
+                    &mut list, 0, end, self.time0, self.time1,
+                ))
+            }
+            ObjectDesc::Obj { name } => Box::new(obj_mtl_load(name)?),
+            ObjectDesc::Animated { keyframes, object } => {
+                let offset = interpolate(
+                    keyframes
+                        .iter()
+                        .filter_map(|k| Some((k.frame, k.translate?))),
+                    self.frame,
+                )
+                .unwrap_or([0.0; 3]);
+                let angle = interpolate(
+                    keyframes
+                        .iter()
+                        .filter_map(|k| Some((k.frame, k.rotate_y?))),
+                    self.frame,
+                )
+                .unwrap_or(0.0);
+                Box::new(Translate {
+                    offset: vec3(offset),
+                    ptr: RotateY::new(self.object(object)?, angle),
+                })
+            }
+        })
+    }
+}
+
+pub fn parse_scene(path: &Path) -> Result<SceneFile, String> {
+    let text = std::fs::read_to_string(path)
+        .map_err(|e| format!("cannot read {}: {}", path.display(), e))?;
+    let extension = path
+        .extension()
+        .and_then(|e| e.to_str())
+        .unwrap_or("")
+        .to_lowercase();
+    match extension.as_str() {
+        "json" => serde_json::from_str(&text).map_err(|e| format!("{}: {}", path.display(), e)),
+        "yaml" | "yml" => {
+            serde_yaml::from_str(&text).map_err(|e| format!("{}: {}", path.display(), e))
+        }
+        _ => Err(format!(
+            "{}: scene files must end in .json, .yaml or .yml",
+            path.display()
+        )),
+    }
+}
+
+//frame 为动画的帧号，用于插值相机与物体的关键帧；cache 在同一个场景的各帧之间保留
+pub fn build_scene(
+    file: &SceneFile,
+    frame: f64,
+    rng: &mut Rng,
+    cache: &mut SceneCache,
+) -> Result<(Color, f64, u32, HittableList, HittableList, Camera), String> {
+    cache.reused = 0;
+    cache.saved_seconds = 0.0;
+    let mut builder = SceneBuilder {
+        texture_descs: &file.textures,
+        textures: HashMap::new(),
+        materials: HashMap::new(),
+        resolving: Vec::new(),
+        material_count: 0,
+        rng,
+        time0: file.camera.time0,
+        time1: file.camera.time1,
+        frame,
+        cache,
+        caching: file.animates_objects(),
+    };
+    //按名字的顺序建纹理，噪声纹理取到的随机数才与 HashMap 的顺序无关
+    let mut names: Vec<&String> = file.textures.keys().collect();
+    names.sort();
+    for name in names {
+        builder.named_texture(name)?;
+    }
+    let mut names: Vec<&String> = file.materials.keys().collect();
+    names.sort();
+    for name in names {
+        let mat = builder.material_desc(&file.materials[name])?;
+        builder.materials.insert(name.clone(), mat);
+    }
+    let mut objects = HittableList::new();
+    for o in &file.objects {
+        objects.add(builder.object(o)?);
+    }
+    let mut lights = HittableList::new();
+    for o in &file.lights {
+        lights.add(builder.object(o)?);
+    }
+    let camera = file.camera_at(frame);
+    Ok((
+        vec3(file.background),
+        file.aspect_ratio,
+        file.width,
+        objects,
+        lights,
+        camera,
+    ))
+}
@@ -3,15 +3,16 @@ use crate::{
     hittable::rect::XyRect,
     hittable::sphere::Sphere,
     hittable::HittableList,
-    material::{diffuselight::DiffuseLight, lambertian::Lambertian},
+    material::{diffuselight::DiffuseLight, lambertian::Lambertian, WithId},
     perlin::Perlin,
+    randoms::Rng,
     texture::noisetexture::NoiseTexture,
     vec3::{Color, Point3, Vec3},
 };
-pub fn simple_light() -> (Color, f64, u32, HittableList, Camera) {
+pub fn simple_light(rng: &mut Rng) -> (Color, f64, u32, HittableList, HittableList, Camera) {
     let mut objects = HittableList::new();
     let pertext = NoiseTexture {
-        noise: Perlin::new(),
+        noise: Perlin::new(rng),
         scale: 4.0,
     };
     objects.add(Box::new(Sphere {
@@ -19,32 +20,49 @@ pub fn simple_light() -> (Color, f64, u32, HittableList, Camera) {
             e: (0.0, -1000.0, 0.0),
         },
         radius: 1000.0,
-        mat_ptr: Lambertian { albedo: pertext },
+        mat_ptr: WithId {
+            id: 1,
+            mat: Lambertian { albedo: pertext },
+        },
     }));
     let pertext = NoiseTexture {
-        noise: Perlin::new(),
+        noise: Perlin::new(rng),
         scale: 4.0,
     };
     objects.add(Box::new(Sphere {
         center: Point3 { e: (0.0, 2.0, 0.0) },
         radius: 2.0,
-        mat_ptr: Lambertian { albedo: pertext },
+        mat_ptr: WithId {
+            id: 1,
+            mat: Lambertian { albedo: pertext },
+        },
     }));
-    let difflight = DiffuseLight::new(Color { e: (4.0, 4.0, 4.0) });
-    objects.add(Box::new(XyRect {
+    let difflight = WithId {
+        id: 2,
+        mat: DiffuseLight::new(Color { e: (4.0, 4.0, 4.0) }),
+    };
+    let rect_light = XyRect {
         x0: 3.0,
         x1: 5.0,
         y0: 1.0,
         y1: 3.0,
         k: -2.0,
         mp: difflight,
-    }));
-    let difflight = DiffuseLight::new(Color { e: (4.0, 4.0, 4.0) });
-    objects.add(Box::new(Sphere {
+    };
+    objects.add(Box::new(rect_light.clone()));
+    let difflight = WithId {
+        id: 2,
+        mat: DiffuseLight::new(Color { e: (4.0, 4.0, 4.0) }),
+    };
+    let sphere_light = Sphere {
         center: Point3 { e: (0.0, 7.0, 0.0) },
         radius: 2.0,
         mat_ptr: difflight,
-    }));
+    };
+    objects.add(Box::new(sphere_light.clone()));
+    let mut lights = HittableList::new();
+    lights.add(Box::new(rect_light));
+    lights.add(Box::new(sphere_light));
     let lookfrom: Point3 = Point3 {
         e: (26.0, 3.0, 6.0),
     };
@@ -55,6 +73,7 @@ pub fn simple_light() -> (Color, f64, u32, HittableList, Camera) {
         aspect_ratio,
         1600,
         objects,
+        lights,
         Camera::new_cam(
             lookfrom,
             lookat,
@@ -2,15 +2,16 @@ use crate::{
     camera::{Camera, NewCamMessage},
     hittable::sphere::Sphere,
     hittable::HittableList,
-    material::lambertian::Lambertian,
+    material::{lambertian::Lambertian, WithId},
     perlin::Perlin,
+    randoms::Rng,
     texture::noisetexture::NoiseTexture,
     vec3::{Color, Point3, Vec3},
 };
-pub fn two_perlin_sphere() -> (Color, f64, u32, HittableList, Camera) {
+pub fn two_perlin_sphere(rng: &mut Rng) -> (Color, f64, u32, HittableList, HittableList, Camera) {
     let mut objects = HittableList::new();
     let pertext = NoiseTexture {
-        noise: Perlin::new(),
+        noise: Perlin::new(rng),
         scale: 4.0,
     };
     objects.add(Box::new(Sphere {
@@ -18,16 +19,22 @@ pub fn two_perlin_sphere() -> (Color, f64, u32, HittableList, Camera) {
             e: (0.0, -1000.0, 0.0),
         },
         radius: 1000.0,
-        mat_ptr: Lambertian { albedo: pertext },
+        mat_ptr: WithId {
+            id: 1,
+            mat: Lambertian { albedo: pertext },
+        },
     }));
     let pertext = NoiseTexture {
-        noise: Perlin::new(),
+        noise: Perlin::new(rng),
         scale: 4.0,
     };
     objects.add(Box::new(Sphere {
         center: Point3 { e: (0.0, 2.0, 0.0) },
         radius: 2.0,
-        mat_ptr: Lambertian { albedo: pertext },
+        mat_ptr: WithId {
+            id: 1,
+            mat: Lambertian { albedo: pertext },
+        },
     }));
     let lookfrom: Point3 = Point3 {
         e: (13.0, 2.0, 3.0),
@@ -39,6 +46,7 @@ pub fn two_perlin_sphere() -> (Color, f64, u32, HittableList, Camera) {
         aspect_ratio,
         1600,
         objects,
+        HittableList::new(),
         Camera::new_cam(
             lookfrom,
             lookat,
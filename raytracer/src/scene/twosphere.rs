@@ -2,11 +2,11 @@ use crate::{
     camera::{Camera, NewCamMessage},
     hittable::sphere::Sphere,
     hittable::HittableList,
-    material::lambertian::Lambertian,
+    material::{lambertian::Lambertian, WithId},
     texture::{checkertexture::CheckerTexture, solodcolor::SolidColor},
     vec3::{Color, Point3, Vec3},
 };
-pub fn two_spheres() -> (Color, f64, u32, HittableList, Camera) {
+pub fn two_spheres() -> (Color, f64, u32, HittableList, HittableList, Camera) {
     let mut objects = HittableList::new();
     let checker = CheckerTexture {
         even: SolidColor::new(Color { e: (0.2, 0.3, 0.1) }),
@@ -17,7 +17,10 @@ pub fn two_spheres() -> (Color, f64, u32, HittableList, Camera) {
             e: (0.0, -10.0, 0.0),
         },
         radius: 10.0,
-        mat_ptr: Lambertian { albedo: checker },
+        mat_ptr: WithId {
+            id: 1,
+            mat: Lambertian { albedo: checker },
+        },
     }));
     let checker = CheckerTexture {
         even: SolidColor::new(Color { e: (0.2, 0.3, 0.1) }),
@@ -28,7 +31,10 @@ pub fn two_spheres() -> (Color, f64, u32, HittableList, Camera) {
             e: (0.0, 10.0, 0.0),
         },
         radius: 10.0,
-        mat_ptr: Lambertian { albedo: checker },
+        mat_ptr: WithId {
+            id: 1,
+            mat: Lambertian { albedo: checker },
+        },
     }));
     let lookfrom: Point3 = Point3 {
         e: (13.0, 2.0, 3.0),
@@ -40,6 +46,7 @@ pub fn two_spheres() -> (Color, f64, u32, HittableList, Camera) {
         aspect_ratio,
         1600,
         objects,
+        HittableList::new(),
         Camera::new_cam(
             lookfrom,
             lookat,
@@ -0,0 +1,186 @@
+//光谱渲染：每条路径带三个波长，Color 的三个分量依次是这三个波长上的值而不是 RGB
+use std::cell::Cell;
+
+use crate::vec3::Color;
+
+pub const LAMBDA_MIN: f64 = 360.0;
+pub const LAMBDA_MAX: f64 = 830.0;
+
+//Smits (1999) 的基底光谱，380–720nm 均分 10 段，按段中心线性插值
+const SMITS_WHITE: [f64; 10] = [
+    1.0000, 1.0000, 0.9999, 0.9993, 0.9992, 0.9998, 1.0000, 1.0000, 1.0000, 1.0000,
+];
+const SMITS_CYAN: [f64; 10] = [
+    0.9710, 0.9426, 1.0007, 1.0007, 1.0007, 1.0007, 0.1564, 0.0000, 0.0000, 0.0000,
+];
+const SMITS_MAGENTA: [f64; 10] = [
+    1.0000, 1.0000, 0.9685, 0.2229, 0.0000, 0.0458, 0.8369, 1.0000, 1.0000, 0.9959,
+];
+const SMITS_YELLOW: [f64; 10] = [
+    0.0001, 0.0000, 0.1088, 0.6651, 1.0000, 1.0000, 0.9996, 0.9586, 0.9685, 0.9840,
+];
+const SMITS_RED: [f64; 10] = [
+    0.1012, 0.0515, 0.0000, 0.0000, 0.0000, 0.0000, 0.8325, 1.0149, 1.0149, 1.0149,
+];
+const SMITS_GREEN: [f64; 10] = [
+    0.0000, 0.0000, 0.0273, 0.7937, 1.0000, 0.9418, 0.1719, 0.0000, 0.0000, 0.0025,
+];
+const SMITS_BLUE: [f64; 10] = [
+    1.0000, 1.0000, 0.8916, 0.3323, 0.0000, 0.0000, 0.0003, 0.0369, 0.0483, 0.0496,
+];
+
+//下面的 CIE 拟合在 [LAMBDA_MIN, LAMBDA_MAX] 上 ȳ 的积分
+const CIE_Y_INTEGRAL: f64 = 106.922_074_506_916_32;
+//等能白光（光谱恒为 1）按 XYZ→sRGB 换算后的 RGB，除以它使等能白光仍为 (1,1,1)
+const WHITE_RGB: [f64; 3] = [
+    1.200_268_165_015_651,
+    0.949_698_991_488_175_8,
+    0.908_295_967_868_092,
+];
+
+fn smits(table: &[f64; 10], lambda: f64) -> f64 {
+    let x = (lambda - 380.0) / 340.0 * 10.0 - 0.5;
+    if x <= 0.0 {
+        return table[0];
+    }
+    if x >= 9.0 {
+        return table[9];
+    }
+    let i = x as usize;
+    let f = x - i as f64;
+    table[i] * (1.0 - f) + table[i + 1] * f
+}
+
+//RGB 在 lambda 处的光谱值（Smits 的方法），对 RGB 线性，所以自发光等大于 1 的值也适用
+pub fn rgb_to_spectrum(c: &Color, lambda: f64) -> f64 {
+    let (r, g, b) = c.e;
+    let s = |t: &[f64; 10]| smits(t, lambda);
+    if r <= g && r <= b {
+        r * s(&SMITS_WHITE)
+            + if g <= b {
+                (g - r) * s(&SMITS_CYAN) + (b - g) * s(&SMITS_BLUE)
+            } else {
+                (b - r) * s(&SMITS_CYAN) + (g - b) * s(&SMITS_GREEN)
+            }
+    } else if g <= r && g <= b {
+        g * s(&SMITS_WHITE)
+            + if r <= b {
+                (r - g) * s(&SMITS_MAGENTA) + (b - r) * s(&SMITS_BLUE)
+            } else {
+                (b - g) * s(&SMITS_MAGENTA) + (r - b) * s(&SMITS_RED)
+            }
+    } else {
+        b * s(&SMITS_WHITE)
+            + if r <= g {
+                (r - b) * s(&SMITS_YELLOW) + (g - r) * s(&SMITS_GREEN)
+            } else {
+                (g - b) * s(&SMITS_YELLOW) + (r - g) * s(&SMITS_RED)
+            }
+    }
+}
+
+//CIE 1931 配色函数的多高斯拟合（Wyman, Sloan, Shirley 2013）
+fn cie_xyz(lambda: f64) -> (f64, f64, f64) {
+    let g = |mu: f64, s1: f64, s2: f64| {
+        let t = (lambda - mu) / if lambda < mu { s1 } else { s2 };
+        (-0.5 * t * t).exp()
+    };
+    (
+        1.056 * g(599.8, 37.9, 31.0) + 0.362 * g(442.0, 16.0, 26.7) - 0.065 * g(501.1, 20.4, 26.2),
+        0.821 * g(568.8, 46.9, 40.5) + 0.286 * g(530.9, 16.3, 31.1),
+        1.217 * g(437.0, 11.8, 36.0) + 0.681 * g(459.0, 26.0, 13.8),
+    )
+}
+
+//一条路径的三个波长：hero 波长均匀分布，另外两个在波长范围内等间隔轮转
+#[derive(Clone, Copy)]
+pub struct SampledWavelengths {
+    pub lambda: [f64; 3],
+}
+
+impl SampledWavelengths {
+    pub fn sample(u: f64) -> Self {
+        let range = LAMBDA_MAX - LAMBDA_MIN;
+        let mut lambda = [0.0; 3];
+        for (i, l) in lambda.iter_mut().enumerate() {
+            let x = u + i as f64 / 3.0;
+            *l = LAMBDA_MIN + range * (x - x.floor());
+        }
+        Self { lambda }
+    }
+    //RGB 颜色在这三个波长上的值
+    pub fn lift(&self, c: &Color) -> Color {
+        Color {
+            e: (
+                rgb_to_spectrum(c, self.lambda[0]),
+                rgb_to_spectrum(c, self.lambda[1]),
+                rgb_to_spectrum(c, self.lambda[2]),
+            ),
+        }
+    }
+    //三个波长上的辐亮度换回线性 sRGB：按配色函数积分的蒙特卡洛估计，三个波长取平均
+    pub fn to_rgb(&self, s: &Color) -> Color {
+        let values = [s.e.0, s.e.1, s.e.2];
+        let (mut x, mut y, mut z) = (0.0, 0.0, 0.0);
+        for (l, v) in self.lambda.iter().zip(values) {
+            let (cx, cy, cz) = cie_xyz(*l);
+            x += v * cx;
+            y += v * cy;
+            z += v * cz;
+        }
+        let k = (LAMBDA_MAX - LAMBDA_MIN) / (3.0 * CIE_Y_INTEGRAL);
+        let (x, y, z) = (x * k, y * k, z * k);
+        Color {
+            e: (
+                (3.240_454_2 * x - 1.537_138_5 * y - 0.498_531_4 * z) / WHITE_RGB[0],
+                (-0.969_266 * x + 1.876_010_8 * y + 0.041_556 * z) / WHITE_RGB[1],
+                (0.055_643_4 * x - 0.204_025_9 * y + 1.057_225_2 * z) / WHITE_RGB[2],
+            ),
+        }
+    }
+}
+
+//只保留 hero 波长：路径经过色散界面后其余波长不再沿同一方向，权重乘 3 以保持三个波长平均后的估计无偏
+pub fn terminate_secondary(c: &Color) -> Color {
+    Color {
+        e: (3.0 * c.e.0, 0.0, 0.0),
+    }
+}
+
+thread_local! {
+    static HERO: Cell<Option<f64>> = const { Cell::new(None) };
+}
+//材质接口不带波长，色散的材质从这里取本线程当前路径的 hero 波长；RGB 渲染时为 None
+pub fn set_hero_wavelength(lambda: Option<f64>) {
+    HERO.with(|h| h.set(lambda));
+}
+pub fn hero_wavelength() -> Option<f64> {
+    HERO.with(|h| h.get())
+}
+
+//随波长变化的折射率，波长以 µm 代入
+#[derive(Clone, Copy)]
+pub enum Dispersion {
+    //n = a + b/λ²
+    Cauchy { a: f64, b: f64 },
+    //n² = 1 + Σ bᵢλ²/(λ² − cᵢ)
+    Sellmeier { b: [f64; 3], c: [f64; 3] },
+}
+
+impl Dispersion {
+    //lambda 以 nm 计
+    pub fn ior(&self, lambda: f64) -> f64 {
+        let l = lambda / 1000.0;
+        let l2 = l * l;
+        match self {
+            Dispersion::Cauchy { a, b } => a + b / l2,
+            Dispersion::Sellmeier { b, c } => {
+                let n2 = 1.0 + (0..3).map(|i| b[i] * l2 / (l2 - c[i])).sum::<f64>();
+                n2.max(1.0).sqrt()
+            }
+        }
+    }
+}
+
+//钠 D 线，RGB 渲染时色散材质取这个波长的折射率
+pub const SODIUM_D: f64 = 589.3;
@@ -0,0 +1,143 @@
+//光线与求交的计数：每个线程各自累计，线程结束时取走合并，热路径上不加锁
+use std::{
+    cell::Cell,
+    ops::{AddAssign, Sub},
+};
+
+#[derive(Clone, Copy, Default)]
+pub struct RayStats {
+    //相机光线、之后各次弹射（含光源子路径与光子）的光线、只判断可见性的阴影光线
+    pub primary: u64,
+    pub secondary: u64,
+    pub shadow: u64,
+    //BvhNode::hit 的调用次数、包围盒求交次数、图元求交次数
+    pub node_visits: u64,
+    pub box_tests: u64,
+    pub primitive_tests: u64,
+}
+
+impl RayStats {
+    const ZERO: RayStats = RayStats {
+        primary: 0,
+        secondary: 0,
+        shadow: 0,
+        node_visits: 0,
+        box_tests: 0,
+        primitive_tests: 0,
+    };
+    pub fn rays(&self) -> u64 {
+        self.primary + self.secondary + self.shadow
+    }
+    //遍历代价：访问的节点数加上图元求交次数
+    pub fn traversal_cost(&self) -> u64 {
+        self.node_visits + self.primitive_tests
+    }
+}
+
+impl AddAssign for RayStats {
+    fn add_assign(&mut self, o: Self) {
+        self.primary += o.primary;
+        self.secondary += o.secondary;
+        self.shadow += o.shadow;
+        self.node_visits += o.node_visits;
+        self.box_tests += o.box_tests;
+        self.primitive_tests += o.primitive_tests;
+    }
+}
+
+impl Sub for RayStats {
+    type Output = Self;
+    fn sub(self, o: Self) -> Self {
+        Self {
+            primary: self.primary - o.primary,
+            secondary: self.secondary - o.secondary,
+            shadow: self.shadow - o.shadow,
+            node_visits: self.node_visits - o.node_visits,
+            box_tests: self.box_tests - o.box_tests,
+            primitive_tests: self.primitive_tests - o.primitive_tests,
+        }
+    }
+}
+
+thread_local! {
+    static STATS: Cell<RayStats> = const { Cell::new(RayStats::ZERO) };
+}
+
+#[inline]
+fn count(f: impl FnOnce(&mut RayStats)) {
+    STATS.with(|s| {
+        let mut v = s.get();
+        f(&mut v);
+        s.set(v);
+    });
+}
+
+#[inline]
+pub fn primary_ray() {
+    count(|s| s.primary += 1);
+}
+#[inline]
+pub fn secondary_ray() {
+    count(|s| s.secondary += 1);
+}
+#[inline]
+pub fn shadow_ray() {
+    count(|s| s.shadow += 1);
+}
+#[inline]
+pub fn node_visit() {
+    count(|s| s.node_visits += 1);
+}
+#[inline]
+pub fn box_test() {
+    count(|s| s.box_tests += 1);
+}
+#[inline]
+pub fn primitive_test() {
+    count(|s| s.primitive_tests += 1);
+}
+
+//本线程到目前为止的计数
+pub fn snapshot() -> RayStats {
+    STATS.with(|s| s.get())
+}
+//取走本线程的计数并清零
+pub fn take() -> RayStats {
+    STATS.with(|s| s.replace(RayStats::ZERO))
+}
+
+//table 时按线程列出计数，最后一行为合计；总是给出每秒的光线数
+pub fn report(per_thread: &[(String, RayStats)], seconds: f64, table: bool) {
+    let mut total = RayStats::default();
+    for (_, s) in per_thread {
+        total += *s;
+    }
+    if table {
+        println!(
+            "{:>10} {:>12} {:>12} {:>12} {:>14} {:>14} {:>14}",
+            "thread", "primary", "secondary", "shadow", "bvh nodes", "box tests", "prim tests"
+        );
+        let row = |name: &str, s: &RayStats| {
+            println!(
+                "{:>10} {:>12} {:>12} {:>12} {:>14} {:>14} {:>14}",
+                name,
+                s.primary,
+                s.secondary,
+                s.shadow,
+                s.node_visits,
+                s.box_tests,
+                s.primitive_tests
+            );
+        };
+        for (name, s) in per_thread {
+            row(name, s);
+        }
+        row("total", &total);
+    }
+    println!(
+        "Rendered {} rays in {:.2}s, {:.3} Mrays/s",
+        total.rays(),
+        seconds,
+        total.rays() as f64 / seconds.max(1e-9) / 1e6
+    );
+}
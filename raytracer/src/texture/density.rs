@@ -0,0 +1,121 @@
+use std::sync::Arc;
+
+use crate::{
+    perlin::{trilinear_interp, Perlin},
+    randoms::clamp,
+    vec3::{mul_vec_dot, Point3},
+};
+
+//参与介质的密度场：给出每一点的密度（消光系数），以及整个场的上界，供 delta tracking 使用
+pub trait DensityField {
+    fn density(&self, p: &Point3) -> f64;
+    fn max_density(&self) -> f64;
+}
+impl<D: DensityField + ?Sized> DensityField for Arc<D> {
+    fn density(&self, p: &Point3) -> f64 {
+        (**self).density(p)
+    }
+    fn max_density(&self) -> f64 {
+        (**self).max_density()
+    }
+}
+
+//Perlin 湍流，低于 cutoff 的部分置 0，其余线性拉伸到 [0, density]，cutoff 越大云团越稀疏
+#[derive(Clone)]
+pub struct NoiseDensity {
+    pub noise: Perlin,
+    pub scale: f64,
+    pub depth: i32,
+    pub cutoff: f64,
+    pub density: f64,
+}
+impl DensityField for NoiseDensity {
+    fn density(&self, p: &Point3) -> f64 {
+        let t = self.noise.turb(&(*p * self.scale), self.depth);
+        self.density * clamp((t - self.cutoff) / (1.0 - self.cutoff), 0.0, 1.0)
+    }
+    fn max_density(&self) -> f64 {
+        self.density
+    }
+}
+
+//从 from 到 to 密度由 density0 线性变为 density1，两端之外保持端点的值
+#[derive(Clone)]
+pub struct GradientDensity {
+    pub from: Point3,
+    pub to: Point3,
+    pub density0: f64,
+    pub density1: f64,
+}
+impl DensityField for GradientDensity {
+    fn density(&self, p: &Point3) -> f64 {
+        let axis = self.to - self.from;
+        let t = clamp(
+            mul_vec_dot(*p - self.from, axis) / axis.length_square(),
+            0.0,
+            1.0,
+        );
+        self.density0 + (self.density1 - self.density0) * t
+    }
+    fn max_density(&self) -> f64 {
+        self.density0.max(self.density1)
+    }
+}
+
+//min 到 max 之间的规则网格，values 按 x 最快、z 最慢存放各格点的密度，格点之间三线性插值，网格外为 0
+#[derive(Clone)]
+pub struct GridDensity {
+    pub min: Point3,
+    pub max: Point3,
+    pub resolution: [usize; 3],
+    pub values: Vec<f64>,
+    max_value: f64,
+}
+impl GridDensity {
+    //values 的个数须为 nx·ny·nz，且每一维至少两个格点
+    pub fn new(min: Point3, max: Point3, resolution: [usize; 3], values: Vec<f64>) -> Option<Self> {
+        if resolution.iter().any(|&n| n < 2)
+            || values.len() != resolution[0] * resolution[1] * resolution[2]
+        {
+            return None;
+        }
+        let max_value = values.iter().copied().fold(0.0, f64::max);
+        Some(Self {
+            min,
+            max,
+            resolution,
+            values,
+            max_value,
+        })
+    }
+    fn at(&self, i: usize, j: usize, k: usize) -> f64 {
+        let [nx, ny, _] = self.resolution;
+        self.values[(k * ny + j) * nx + i]
+    }
+}
+impl DensityField for GridDensity {
+    fn density(&self, p: &Point3) -> f64 {
+        let lo = [self.min.e.0, self.min.e.1, self.min.e.2];
+        let hi = [self.max.e.0, self.max.e.1, self.max.e.2];
+        let q = [p.e.0, p.e.1, p.e.2];
+        let mut cell = [0; 3];
+        let mut frac = [0.0; 3];
+        for a in 0..3 {
+            let t = (q[a] - lo[a]) / (hi[a] - lo[a]);
+            if !(0.0..=1.0).contains(&t) {
+                return 0.0;
+            }
+            let x = t * (self.resolution[a] - 1) as f64;
+            cell[a] = (x.floor() as usize).min(self.resolution[a] - 2);
+            frac[a] = x - cell[a] as f64;
+        }
+        let mut c = [0.0; 8];
+        for (t, v) in c.iter_mut().enumerate() {
+            *v = self.at(cell[0] + t / 4, cell[1] + (t % 4) / 2, cell[2] + t % 2);
+        }
+        trilinear_interp(&c, frac[0], frac[1], frac[2])
+    }
+    fn max_density(&self) -> f64 {
+        self.max_value
+    }
+}
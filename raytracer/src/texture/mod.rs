@@ -1,10 +1,18 @@
 pub mod checkertexture;
+pub mod density;
 pub mod imagetexture;
 pub mod noisetexture;
 pub mod objloadingtexture;
 pub mod solodcolor;
+use std::sync::Arc;
+
 use crate::vec3::{Color, Point3};
 
 pub trait Texture {
     fn value(&self, u: f64, v: f64, p: &Point3) -> Color;
 }
+impl<T: Texture + ?Sized> Texture for Arc<T> {
+    fn value(&self, u: f64, v: f64, p: &Point3) -> Color {
+        (**self).value(u, v, p)
+    }
+}
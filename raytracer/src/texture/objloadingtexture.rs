@@ -18,13 +18,14 @@ pub struct ObjLoadingTexture {
     pub tex: SolidColor,
 }
 impl ObjLoadingTexture {
-    pub fn new(data: &Path) -> Self {
-        let img_: DynamicImage = image::open(data).expect("failed");
+    pub fn new(data: &Path) -> Result<Self, String> {
+        let img_: DynamicImage = image::open(data)
+            .map_err(|e| format!("cannot load texture {}: {}", data.display(), e))?;
         let rgb_img: RgbImage = match img_ {
             DynamicImage::ImageRgb8(rgb_img) => rgb_img,
             _ => img_.to_rgb8(),
         };
-        Self {
+        Ok(Self {
             have_image: true,
             img: Arc::new(rgb_img.clone()),
             width: rgb_img.width(),
@@ -32,20 +33,15 @@ impl ObjLoadingTexture {
             tex: SolidColor {
                 color_value: crate::vec3::Vec3 { e: (0.0, 0.0, 0.0) },
             },
-        }
+        })
     }
+    //纯色不用图片，放一张空图
     pub fn new_solid_color((a, b, c): (f64, f64, f64)) -> Self {
-        let data = std::path::Path::new("raytracer/src/sourcepictures/earthmap.jpg");
-        let img_: DynamicImage = image::open(data).expect("failed");
-        let rgb_img: RgbImage = match img_ {
-            DynamicImage::ImageRgb8(rgb_img) => rgb_img,
-            _ => img_.to_rgb8(),
-        };
         Self {
             have_image: false,
-            img: Arc::new(rgb_img.clone()),
-            width: rgb_img.width(),
-            height: rgb_img.height(),
+            img: Arc::new(RgbImage::new(0, 0)),
+            width: 0,
+            height: 0,
             tex: SolidColor {
                 color_value: crate::vec3::Vec3 { e: (a, b, c) },
             },
@@ -0,0 +1,165 @@
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+use crate::{
+    aov::{Aov, AovBuffer},
+    bdpt::Splat,
+    film::{Film, Filter},
+    framebuffer::luminance,
+    integrator::PathSample,
+    vec3::Color,
+};
+
+#[derive(Clone, Copy, PartialEq)]
+pub enum TileOrder {
+    Scanline,
+    //从图像中心向外螺旋
+    Spiral,
+}
+
+//图像上的一块矩形区域，x0,y0 为左上角（图像坐标，y 向下）
+#[derive(Clone, Copy)]
+pub struct Tile {
+    pub x0: u32,
+    pub y0: u32,
+    pub width: u32,
+    pub height: u32,
+}
+
+impl Tile {
+    pub fn pixel_count(&self) -> u32 {
+        self.width * self.height
+    }
+}
+
+//线程空闲时从队列里取下一块，取完为止
+pub struct TileQueue {
+    tiles: Vec<Tile>,
+    next: AtomicUsize,
+}
+
+impl TileQueue {
+    pub fn new(width: u32, height: u32, tile_size: u32, order: TileOrder) -> Self {
+        let size = tile_size.max(1);
+        let nx = (width + size - 1) / size;
+        let ny = (height + size - 1) / size;
+        let cells = match order {
+            TileOrder::Scanline => (0..ny)
+                .flat_map(|ty| (0..nx).map(move |tx| (tx, ty)))
+                .collect(),
+            TileOrder::Spiral => spiral(nx, ny),
+        };
+        let tiles = cells
+            .into_iter()
+            .map(|(tx, ty)| Tile {
+                x0: tx * size,
+                y0: ty * size,
+                width: size.min(width - tx * size),
+                height: size.min(height - ty * size),
+            })
+            .collect();
+        Self {
+            tiles,
+            next: AtomicUsize::new(0),
+        }
+    }
+    pub fn len(&self) -> usize {
+        self.tiles.len()
+    }
+    pub fn is_empty(&self) -> bool {
+        self.tiles.is_empty()
+    }
+    pub fn next_tile(&self) -> Option<Tile> {
+        let i = self.next.fetch_add(1, Ordering::Relaxed);
+        self.tiles.get(i).copied()
+    }
+}
+
+//从中心格子开始按 右、下、左、上 的顺序绕圈，步长每两次加一，跳过网格外的格子
+fn spiral(nx: u32, ny: u32) -> Vec<(u32, u32)> {
+    let total = (nx * ny) as usize;
+    let mut cells = Vec::with_capacity(total);
+    let (mut x, mut y) = ((nx as i64 - 1) / 2, (ny as i64 - 1) / 2);
+    let dirs = [(1, 0), (0, 1), (-1, 0), (0, -1)];
+    let mut step = 1;
+    let mut d = 0;
+    if total > 0 {
+        cells.push((x as u32, y as u32));
+    }
+    while cells.len() < total {
+        for _ in 0..2 {
+            let (dx, dy) = dirs[d % 4];
+            for _ in 0..step {
+                x += dx;
+                y += dy;
+                if x >= 0 && y >= 0 && x < nx as i64 && y < ny as i64 {
+                    cells.push((x as u32, y as u32));
+                }
+            }
+            d += 1;
+        }
+        step += 1;
+    }
+    cells
+}
+
+//一块渲染完的像素，存的是各采样的颜色之和、亮度平方和与采样数，合并时再除；
+//film 里是按滤波权重摊到周围像素上的结果，区域比块大一圈；splats 为 bdpt 连到相机上、可能落在块外的贡献；
+//cost 为各采样遍历 BVH 的代价之和
+pub struct TileBuffer {
+    pub tile: Tile,
+    pub sum: Vec<Color>,
+    pub sum_sq: Vec<f64>,
+    pub samples: Vec<u32>,
+    pub aov: AovBuffer,
+    pub film: Film,
+    pub splats: Vec<Splat>,
+    pub cost: Vec<u64>,
+}
+
+impl TileBuffer {
+    pub fn new(
+        tile: Tile,
+        aovs: &[Aov],
+        filter: Filter,
+        image_width: u32,
+        image_height: u32,
+    ) -> Self {
+        let n = tile.pixel_count() as usize;
+        Self {
+            tile,
+            sum: vec![Color::new(); n],
+            sum_sq: vec![0.0; n],
+            samples: vec![0; n],
+            aov: AovBuffer::new(tile.width, tile.height, aovs),
+            film: Film::padded(
+                filter,
+                tile.x0,
+                tile.y0,
+                tile.width,
+                tile.height,
+                image_width,
+                image_height,
+            ),
+            splats: vec![],
+            cost: vec![0; n],
+        }
+    }
+    //i,j 为图像坐标，(x,y) 为采样在图像上的连续坐标，落在像素 (i,j) 里
+    pub fn add(&mut self, i: u32, j: u32, x: f64, y: f64, s: &PathSample) {
+        self.film.splat(x, y, s.radiance);
+        let (x, y) = (i - self.tile.x0, j - self.tile.y0);
+        self.aov.add(x, y, s);
+        let c = s.radiance;
+        let idx = (y * self.tile.width + x) as usize;
+        let y = luminance(&c);
+        if y.is_finite() {
+            self.sum_sq[idx] += y * y;
+        }
+        self.sum[idx] += c;
+        self.samples[idx] += 1;
+    }
+    pub fn add_cost(&mut self, i: u32, j: u32, cost: u64) {
+        let idx = ((j - self.tile.y0) * self.tile.width + i - self.tile.x0) as usize;
+        self.cost[idx] += cost;
+    }
+}
@@ -0,0 +1,91 @@
+#[derive(Clone, Copy, PartialEq)]
+pub enum ToneOperator {
+    //只乘曝光，超过 1 的部分截断
+    Linear,
+    //white 为映射到 1 的亮度，None 时为 L/(1+L)
+    Reinhard { white: Option<f32> },
+    Aces,
+    Hable,
+}
+
+#[derive(Clone, Copy)]
+pub struct ToneMapper {
+    pub operator: ToneOperator,
+    //曝光，单位 EV，每加 1 亮度翻倍
+    pub exposure: f32,
+    //写 8 位图像时是否做 sRGB 编码，法线、深度等数据类 AOV 不做
+    pub srgb: bool,
+}
+
+impl ToneMapper {
+    pub fn from_name(name: &str, exposure: f64, white: Option<f64>) -> Option<Self> {
+        let operator = match name {
+            "linear" => ToneOperator::Linear,
+            "reinhard" => ToneOperator::Reinhard {
+                white: white.map(|w| w as f32),
+            },
+            "aces" => ToneOperator::Aces,
+            "hable" => ToneOperator::Hable,
+            _ => return None,
+        };
+        Some(Self {
+            operator,
+            exposure: exposure as f32,
+            srgb: true,
+        })
+    }
+    //原样截断到 [0,1]
+    pub fn raw() -> Self {
+        Self {
+            operator: ToneOperator::Linear,
+            exposure: 0.0,
+            srgb: false,
+        }
+    }
+    //线性辐亮度映射到 [0,1] 的显示值（仍为线性，之后再做 sRGB 编码）
+    pub fn map(&self, c: [f32; 3]) -> [f32; 3] {
+        let k = self.exposure.exp2();
+        let c = [c[0] * k, c[1] * k, c[2] * k];
+        let out = match self.operator {
+            ToneOperator::Linear => c,
+            //按亮度压缩，保持色相
+            ToneOperator::Reinhard { white } => {
+                let l = 0.2126 * c[0] + 0.7152 * c[1] + 0.0722 * c[2];
+                if l <= 0.0 {
+                    [0.0; 3]
+                } else {
+                    let lw = match white {
+                        Some(w) => l * (1.0 + l / (w * w)) / (1.0 + l),
+                        None => l / (1.0 + l),
+                    };
+                    [c[0] * lw / l, c[1] * lw / l, c[2] * lw / l]
+                }
+            }
+            //Narkowicz 对 ACES RRT+ODT 的拟合
+            ToneOperator::Aces => c.map(|x| {
+                let x = 0.6 * x;
+                x * (2.51 * x + 0.03) / (x * (2.43 * x + 0.59) + 0.14)
+            }),
+            //Uncharted 2 的曲线，白点 11.2
+            ToneOperator::Hable => {
+                let w = hable(11.2);
+                c.map(|x| hable(2.0 * x) / w)
+            }
+        };
+        out.map(|x| if x.is_nan() { 0.0 } else { x.clamp(0.0, 1.0) })
+    }
+}
+
+fn hable(x: f32) -> f32 {
+    let (a, b, c, d, e, f) = (0.15, 0.50, 0.10, 0.20, 0.02, 0.30);
+    (x * (a * x + c * b) + d * e) / (x * (a * x + b) + d * f) - e / f
+}
+
+//线性值到 sRGB 编码值
+pub fn srgb_encode(x: f32) -> f32 {
+    if x <= 0.0031308 {
+        12.92 * x
+    } else {
+        1.055 * x.powf(1.0 / 2.4) - 0.055
+    }
+}